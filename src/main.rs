@@ -8,7 +8,8 @@ use ollama_rs::Ollama;
 use ollama_rs::generation::completion::request::GenerationRequest;
 use std::fs::{File, create_dir_all};
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // Import custom modules for version extraction and RAG functionality
 mod rag;
@@ -36,30 +37,32 @@ fn show_banner() {
 // Main asynchronous function - entry point of the application
 #[tokio::main]
 async fn main() {
-    // Display the ASCII art banner
-    show_banner();
-
-    // Display version information extracted from Cargo.toml
-    toml_extract::main();
-
-    // Print a welcome message with instructions for getting help
-    let msg = format!(
-        "Welcome to Ollama Textual AI Generator!\n\t Use: \"cargo run -- --bighelp\" for quick help."
-    );
-    println!("\t {}", msg.bright_yellow().bold());
-    println!(
-        "\t {}",
-        "-------------------------------------------------\n"
-            .bright_yellow()
-            .bold()
-    );
-
     // Parse command-line arguments using clap
     let matches = parse_arguments();
 
-    // Handle RAG-specific commands (build, remove, status) if provided
-    if let Some(rag_command) = matches.get_one::<String>("rag") {
-        handle_rag_command(rag_command).await;
+    // JSON output is meant for scripts, so it is not preceded by the banner
+    if !matches.get_flag("json") {
+        // Display the ASCII art banner
+        show_banner();
+
+        // Display version information extracted from Cargo.toml
+        toml_extract::main();
+
+        // Print a welcome message with instructions for getting help
+        let msg = "Welcome to Ollama Textual AI Generator!\n\t Use: \"cargo run -- --bighelp\" for quick help.".to_string();
+        println!("\t {}", msg.bright_yellow().bold());
+        println!(
+            "\t {}",
+            "-------------------------------------------------\n"
+                .bright_yellow()
+                .bold()
+        );
+    }
+
+    // Handle RAG-specific commands (build, watch, remove, status, export, search, create, configure, list, delete) if provided
+    if let Some(rag_args) = matches.get_many::<String>("rag") {
+        let rag_args: Vec<String> = rag_args.cloned().collect();
+        handle_rag_command(&rag_args, &matches).await;
         return; // Exit early after handling RAG command
     }
 
@@ -73,55 +76,127 @@ async fn main() {
     }
 
     // Check if user wants to query the local RAG knowledge base
-    let use_local = matches.get_flag("use_local");
+    // (strict local mode answers only from the knowledge base, so it implies --use-local)
+    let strict_local = matches.get_flag("strict_local");
+    let use_local = matches.get_flag("use_local") || strict_local;
+
+    // Metadata filters restricting which documents the local search considers
+    let filter = match parse_filter(&matches) {
+        Ok(filter) => filter,
+        Err(e) => {
+            colour_print(&format!("\t {}", e), "red");
+            return;
+        }
+    };
+
+    // If using local RAG, try to answer with local model first
+    let kb_name = matches
+        .get_one::<String>("kb")
+        .map(String::as_str)
+        .unwrap_or(rag::collections::DEFAULT_COLLECTION);
+    let retrieval = match retrieval_settings(&matches) {
+        Ok(retrieval) => retrieval,
+        Err(e) => {
+            colour_print(&format!("\t {}", e), "red");
+            return;
+        }
+    };
+
+    // Extract generation parameters from command-line arguments (used by both paths)
+    let model = matches.get_one::<String>("model").unwrap().to_string();
+
+    // Parse the number of results to generate
+    let num_results: usize = matches
+        .get_one::<String>("num_results")
+        .unwrap()
+        .parse()
+        .expect("Invalid number of results");
+
+    // A conversation asks questions in turn until the user ends it, so it has no single prompt
+    if matches.get_flag("chat") {
+        handle_local_rag_chat(kb_name, &filter, &retrieval, &model, &matches).await;
+        return;
+    }
 
     // Retrieve the user's prompt from various sources (CLI, file, or interactive input)
     let prompt = get_prompt(&matches);
 
-// print the prompt that will be sent to the model
-    let msg = format!("Prompt ").bright_yellow().bold();
+    // print the prompt that will be sent to the model
+    let msg = "Prompt ".to_string().bright_yellow().bold();
     println!("\t {}: {}\n", msg, prompt.bright_green().bold());
 
-    // If using local RAG, try to answer with local model first
+    let mut fallback_reason = None;
     if use_local {
-        if let Ok(response) = handle_local_rag_query(&prompt).await {
-            // Extract output parameters for file saving
-            let output_file = matches.get_one::<String>("output").unwrap();
-            let output_dir = "0_out"; // Standard output directory
-            let model = "llama3.2 (local RAG)"; // Indicate it's using local RAG
-
-            // Generate unique output file path
-            let output_file_with_path = generate_unique_output_path(output_dir, output_file);
-            let msg = format!("Output filepath ").bright_yellow().bold();
-            println!(
-                "\t {}: {}",
-                msg,
-                output_file_with_path.bright_green().bold()
-            );
+        let result = match open_local_model(kb_name) {
+            Ok(rag_system) => {
+                handle_local_rag_query(
+                    &rag_system,
+                    &prompt,
+                    "",
+                    &filter,
+                    &retrieval,
+                    &model,
+                    num_results,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(answer) => {
+                // Extract output parameters for file saving
+                let output_file = matches.get_one::<String>("output").unwrap();
+                let output_dir = "0_out"; // Standard output directory
+
+                // Generate unique output file path
+                let output_file_with_path = generate_unique_output_path(output_dir, output_file);
+                let msg = "Output filepath ".to_string().bright_yellow().bold();
+                println!(
+                    "\t {}: {}",
+                    msg,
+                    output_file_with_path.bright_green().bold()
+                );
 
-            // Save response to file and display
-            println!("{}", response);
-            handle_success_local_rag(vec![response], &output_file_with_path, &prompt, model).await;
-            return; // Exit early if local RAG successfully answered
+                // Display the answers in colour, then save them to file as plain Markdown
+                for i in 0..answer.answers.len() {
+                    println!("{}", answer.render_terminal(i));
+                }
+                let grounding = format!("Grounded in the local knowledge base '{}'", kb_name);
+                let save_context = matches.get_flag("save_context");
+                handle_success_local_rag(
+                    &answer,
+                    &output_file_with_path,
+                    &prompt,
+                    &grounding,
+                    save_context,
+                )
+                .await;
+                return; // Exit early if local RAG successfully answered
+            }
+            Err(e) if strict_local => {
+                // Strict local mode never falls back to an ungrounded answer
+                handle_strict_local_failure(&e, &matches, &prompt, kb_name);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                // Problems with the knowledge base or the query have not been shown yet
+                if let LocalRagError::Failed(reason) = &e {
+                    colour_print(&format!("\t {}", reason), "yellow");
+                }
+                colour_print("\t Falling back to Ollama model...", "cyan");
+                fallback_reason = Some(e.to_string());
+            }
         }
-        // If local RAG fails, continue to standard Ollama processing
     }
+    // If local RAG fails, continue to standard Ollama processing
 
-    // Extract standard Ollama generation parameters from command-line arguments
-    let model = matches.get_one::<String>("model").unwrap().to_string();
+    // Extract standard Ollama output parameters from command-line arguments
     let output_file = matches.get_one::<String>("output").unwrap();
     let output_dir = "0_out"; // Standard output directory
 
-    // Parse the number of results to generate
-    let num_results: usize = matches
-        .get_one::<String>("num_results")
-        .unwrap()
-        .parse()
-        .expect("Invalid number of results");
-
     // Generate a unique output file path to avoid overwriting existing files
     let output_file_with_path = generate_unique_output_path(output_dir, output_file);
-    let msg = format!("Output filepath ").bright_yellow().bold();
+    let msg = "Output filepath ".to_string().bright_yellow().bold();
     println!(
         "\t {}: {}",
         msg,
@@ -136,7 +211,27 @@ async fn main() {
 
     // Process the result and either save successful responses or display error
     match res {
-        Ok(response) => handle_success(response, &output_file_with_path, &prompt, &model).await,
+        Ok(response) => {
+            // Label answers that were meant to come from the knowledge base but did not
+            let grounding = fallback_reason.map(|reason| {
+                colour_print(
+                    "\t ⚠ This answer is NOT grounded in your documents (general model fallback).",
+                    "red",
+                );
+                format!(
+                    "Not grounded: the local knowledge base '{}' could not answer ({}), so this answer comes from the general model without document context",
+                    kb_name, reason
+                )
+            });
+            handle_success(
+                response,
+                &output_file_with_path,
+                &prompt,
+                &model,
+                grounding.as_deref(),
+            )
+            .await
+        }
         Err(_) => handle_failure(),
     }
 }
@@ -144,47 +239,163 @@ async fn main() {
 // Display comprehensive help message with examples for all major features
 fn get_big_help() {
     // Standard AI generation examples
-    let msg = format!("\n\t 🤖 Standard AI Generation:")
+    let msg = "\n\t 🤖 Standard AI Generation:"
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
-    let msg = format!("\t cargo run -- --prompt \"What is the capital of France?\"  --output \"result.md\"  --model \"llama3.2\"  --num-results 2").bright_cyan().bold();
+    let msg = "\t cargo run -- --prompt \"What is the capital of France?\"  --output \"result.md\"  --model \"llama3.2\"  --num-results 2".to_string().bright_cyan().bold();
     println!("{}", msg);
 
     // RAG (Retrieval-Augmented Generation) command examples
-    let msg = format!("\n\t 📚 RAG (Retrieval-Augmented Generation) Commands:")
+    let msg = "\n\t 📚 RAG (Retrieval-Augmented Generation) Commands:"
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
-    let msg = format!(
+    let msg =
         "\t cargo run -- --rag build                    # Build local model from data/ directory"
-    )
-    .bright_cyan()
-    .bold();
+            .to_string()
+            .bright_cyan()
+            .bold();
     println!("{}", msg);
-    let msg = format!("\t cargo run -- --rag status                   # Check local model status")
+    let msg = "\t cargo run -- --rag build --full             # Discard the index and rebuild from scratch"
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
-    let msg = format!("\t cargo run -- --rag remove                   # Remove local model")
+    let msg = "\t cargo run -- --rag watch                    # Re-index data/ automatically as files change"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag status                   # Check local model status"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag export                   # Export local model as JSON to agentic/export/"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag remove                   # Remove local model"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+
+    // Named knowledge base (collection) examples
+    let msg = "\n\t 🗂️  Named Knowledge Bases:"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag create legal --data ./contracts   # Create a knowledge base"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag build legal                       # Build it"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag create notes --data ./notes --chunk-strategy paragraph --chunk-size 300 --chunk-overlap 50"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag configure legal --chunk-strategy window --chunk-unit tokens --chunk-size 512 --chunk-overlap 64"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg =
+        "\t cargo run -- --rag list                              # List knowledge bases and stats"
+            .to_string()
+            .bright_cyan()
+            .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --kb legal --prompt \"What is the notice period?\""
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
 
     // Local knowledge base querying examples
-    let msg = format!("\n\t 🔍 Using Local Knowledge Base:")
+    let msg = "\n\t 🔍 Using Local Knowledge Base:"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"Tell me about AstroBill\""
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"What is the notice period?\" --filter type=pdf --filter path=contracts/** --since 2025-01-01"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag search \"notice period\" --top-k 5     # Show matching chunks without an answer"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --rag search '\"oil change\" +engine -diesel source:manuals/**'  # Phrases, required/excluded terms and fields"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --query-syntax --prompt 'How do I reset it? title:astrobill'  # Use the query syntax in a question"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"What is the notice period?\" --top-k 5 --min-score 0.3 --max-context-tokens 3000 --expand 1"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"Compare the two leases\" --mmr 0.7 --max-per-doc 2  # Spread context over documents"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"What is the notice period?\" --rerank --rerank-model \"llama3.2:1b\"  # Let a model re-rank candidates"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --use-local --prompt \"notice?\" --multi-query 3 --hyde --verbose  # Search for rewritten forms of the question"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --chat --kb manuals --prompt \"What does the Astrobill weigh?\"  # Ask follow-up questions in turn"
+        .to_string()
+        .bright_cyan()
+        .bold();
+    println!("{}", msg);
+    let msg = "\t cargo run -- --strict-local --prompt \"What is the notice period?\"  # Never fall back to the general model"
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
-    let msg = format!("\t cargo run -- --use-local --prompt \"Tell me about AstroBill\"")
+    let msg = "\t cargo run -- --use-local --prompt \"What is maintenance?\" --save-context  # Keep the retrieved context in the output file"
+        .to_string()
         .bright_cyan()
         .bold();
     println!("{}", msg);
 
     // Supported file types information
-    let msg = format!("\n\t 📁 Supported file types in data/ directory: PDF, TXT, MD")
-        .bright_yellow()
-        .bold();
+    let msg =
+        "\n\t 📁 Supported file types in data/ directory: PDF, TXT, MD, DOCX, ODT, HTML, EPUB, RTF"
+            .to_string()
+            .bright_yellow()
+            .bold();
     println!("{}", msg);
 }
 
@@ -246,7 +457,170 @@ fn parse_arguments() -> clap::ArgMatches {
                 .short('r')
                 .long("rag")
                 .required(false)
-                .help("RAG system command: 'build', 'remove', or 'status'."),
+                .num_args(1..=2)
+                .value_names(["COMMAND", "NAME"])
+                .help("RAG system command: 'build', 'watch', 'remove', 'status', 'export', 'create', 'configure', 'list', or 'delete', optionally followed by a knowledge base name; or 'search' followed by a query."),
+        )
+        .arg(
+            Arg::new("full")
+                .long("full")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--rag build': discard the existing index and re-extract every file."),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .help("With '--rag build' or '--rag watch': number of documents to extract in parallel (default: number of CPU cores)."),
+        )
+        .arg(
+            Arg::new("data")
+                .long("data")
+                .action(clap::ArgAction::Append)
+                .help("With '--rag create': a data directory for the new knowledge base (repeatable)."),
+        )
+        .arg(
+            Arg::new("chunk_strategy")
+                .long("chunk-strategy")
+                .value_parser(["sentence", "window", "paragraph", "recursive"])
+                .help("With '--rag create' or '--rag configure': how plain text is split into chunks."),
+        )
+        .arg(
+            Arg::new("chunk_size")
+                .long("chunk-size")
+                .value_parser(clap::value_parser!(usize))
+                .help("With '--rag create' or '--rag configure': maximum chunk size (default 500)."),
+        )
+        .arg(
+            Arg::new("chunk_overlap")
+                .long("chunk-overlap")
+                .value_parser(clap::value_parser!(usize))
+                .help("With '--rag create' or '--rag configure': text repeated between neighbouring chunks (default 0)."),
+        )
+        .arg(
+            Arg::new("chunk_unit")
+                .long("chunk-unit")
+                .value_parser(["words", "tokens"])
+                .help("With '--rag create' or '--rag configure': measure chunk size and overlap in words or approximate tokens."),
+        )
+        .arg(
+            Arg::new("top_k")
+                .long("top-k")
+                .short('k')
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .help("With '--use-local' or '--rag search': number of chunks to retrieve (default 3 for queries, 5 for '--rag search')."),
+        )
+        .arg(
+            Arg::new("min_score")
+                .long("min-score")
+                .value_parser(parse_fraction)
+                .help("With '--use-local' or '--rag search': minimum relevance from 0 to 1 for a chunk to be used (default 0.1 for queries)."),
+        )
+        .arg(
+            Arg::new("max_context_tokens")
+                .long("max-context-tokens")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("With '--use-local': maximum size of the retrieved text passed to the model, in approximate tokens (default 2000)."),
+        )
+        .arg(
+            Arg::new("expand")
+                .long("expand")
+                .value_parser(clap::value_parser!(u16))
+                .help("With '--use-local': add this many neighbouring chunks from the same document on each side of every hit (default 0)."),
+        )
+        .arg(
+            Arg::new("mmr")
+                .long("mmr")
+                .value_parser(parse_fraction)
+                .help("With '--use-local': pick chunks by maximal marginal relevance, trading relevance (1) against diversity (0), e.g. 0.7."),
+        )
+        .arg(
+            Arg::new("max_per_doc")
+                .long("max-per-doc")
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .help("With '--use-local': use at most this many chunks from any one document."),
+        )
+        .arg(
+            Arg::new("rerank")
+                .long("rerank")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local': retrieve more candidates and have an Ollama model rate their relevance before keeping the best --top-k."),
+        )
+        .arg(
+            Arg::new("rerank_model")
+                .long("rerank-model")
+                .help("With '--rerank': the Ollama model that rates the candidates (default: the --model)."),
+        )
+        .arg(
+            Arg::new("rerank_prompt")
+                .long("rerank-prompt")
+                .help("With '--rerank': file with the rating prompt, using {question} and {passage} placeholders."),
+        )
+        .arg(
+            Arg::new("rerank_candidates")
+                .long("rerank-candidates")
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .help("With '--rerank': number of candidates to retrieve and rate (default 30)."),
+        )
+        .arg(
+            Arg::new("multi_query")
+                .long("multi-query")
+                .value_parser(clap::value_parser!(u16).range(1..))
+                .help("With '--use-local': also search for this many paraphrases of the question written by an Ollama model, combining the results."),
+        )
+        .arg(
+            Arg::new("hyde")
+                .long("hyde")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local': also search for a hypothetical answer written by an Ollama model (HyDE)."),
+        )
+        .arg(
+            Arg::new("keywords")
+                .long("keywords")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local': also search for the keywords of long questions, without the common words."),
+        )
+        .arg(
+            Arg::new("rewrite_model")
+                .long("rewrite-model")
+                .help("With '--multi-query', '--hyde' or '--chat': the Ollama model that rewrites the question (default: the --model)."),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local': show the search queries used for the question."),
+        )
+        .arg(
+            Arg::new("query_syntax")
+                .long("query-syntax")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local' or '--chat': read the question as a query (\"phrases\", +required, -excluded, OR, title:, source:) instead of plain words."),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--rag search': print the results as JSON (without the banner) for scripting."),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .action(clap::ArgAction::Append)
+                .help("With '--use-local' or '--rag search': only search documents whose metadata matches KEY=VALUE, e.g. type=pdf or path=contracts/** (repeatable)."),
+        )
+        .arg(
+            Arg::new("since")
+                .long("since")
+                .help("With '--use-local' or '--rag search': only search documents created or modified on or after this date (YYYY-MM-DD)."),
+        )
+        .arg(
+            Arg::new("kb")
+                .long("kb")
+                .required(false)
+                .help("Name of the knowledge base to use (default: data/ indexed into agentic/)."),
         )
         .arg(
             Arg::new("use_local")
@@ -255,6 +629,24 @@ fn parse_arguments() -> clap::ArgMatches {
                 .action(clap::ArgAction::SetTrue)
                 .help("Use local RAG knowledge base for query processing."),
         )
+        .arg(
+            Arg::new("chat")
+                .long("chat")
+                .action(clap::ArgAction::SetTrue)
+                .help("Hold a conversation with the local knowledge base: each follow-up question is rewritten into a standalone question using the chat history before searching. The transcript is saved with the sources of every answer."),
+        )
+        .arg(
+            Arg::new("save_context")
+                .long("save-context")
+                .action(clap::ArgAction::SetTrue)
+                .help("With '--use-local': append the retrieved context given to the model to the output file, in a collapsible section."),
+        )
+        .arg(
+            Arg::new("strict_local")
+                .long("strict-local")
+                .action(clap::ArgAction::SetTrue)
+                .help("Answer only from the local knowledge base (implies --use-local): if it has no relevant material, answer \"Not found in the documents.\" and exit with an error instead of falling back to the general model."),
+        )
         .get_matches()
 }
 
@@ -273,7 +665,7 @@ fn get_prompt(matches: &clap::ArgMatches) -> String {
     }
     // If no prompt source specified, prompt user for interactive input
     else {
-        let my_message = format!("\t Enter the prompt : ");
+        let my_message = "\t Enter the prompt : ".to_string();
         let my_prompt: String = get_input(&my_message).expect("\t Failed to receive the value...");
 
         // Confirm to user that prompt has been set
@@ -296,7 +688,7 @@ async fn generate_response(
     num_results: usize,
 ) -> Result<Vec<String>, String> {
     // Display the prompt that will be sent to the model
-    let msg = format!("Prompt ").bright_yellow().bold();
+    let msg = "Prompt ".to_string().bright_yellow().bold();
     println!("\t {}: {}", msg, prompt.bright_green().bold());
 
     // Convert parameters to owned strings for use in async operations
@@ -330,7 +722,14 @@ async fn generate_response(
 //   - output_file: Path where the results should be saved
 //   - prompt: Original prompt that was sent to the model
 //   - model: Name of the model that generated the responses
-async fn handle_success(responses: Vec<String>, output_file: &str, prompt: &str, model: &str) {
+//   - grounding: Note recorded when a local RAG query fell back to this generation
+async fn handle_success(
+    responses: Vec<String>,
+    output_file: &str,
+    prompt: &str,
+    model: &str,
+    grounding: Option<&str>,
+) {
     // Display header for the responses
     colour_print("\t Responses:", "yellow");
 
@@ -341,6 +740,9 @@ async fn handle_success(responses: Vec<String>, output_file: &str, prompt: &str,
     // Write markdown-formatted header information to the file
     writeln!(file, "# Ollama Generation Result\n").unwrap();
     writeln!(file, "## Model: {}\n", model).unwrap();
+    if let Some(grounding) = grounding {
+        writeln!(file, "## Grounding\n\n{}\n", grounding).unwrap();
+    }
     writeln!(file, "## Prompt\n\n{}", prompt).unwrap();
 
     // Display each response to the terminal and write to file
@@ -354,24 +756,29 @@ async fn handle_success(responses: Vec<String>, output_file: &str, prompt: &str,
     }
 
     // Add a blank line at the end of the markdown file for proper formatting
-    writeln!(file, "").unwrap();
+    writeln!(file).unwrap();
 
     // Confirm successful file save to user
-    let msg = format!("Responses saved to file: ").bright_yellow().bold();
+    let msg = "Responses saved to file: "
+        .to_string()
+        .bright_yellow()
+        .bold();
     println!("\t {}: {}", msg, output_file.bright_green().bold());
 }
 
 // Handle successful local RAG response generation by saving results to file
 // Parameters:
-//   - responses: Vector of generated text responses from local RAG
+//   - answer: The generated answers, the model that wrote them and their sources
 //   - output_file: Path where the results should be saved
 //   - prompt: Original prompt that was sent to the model
-//   - model: Name of the model that generated the responses
+//   - grounding: Whether (and from which knowledge base) the answer is grounded
+//   - save_context: Whether to append the context the model was given, for auditing
 async fn handle_success_local_rag(
-    responses: Vec<String>,
+    answer: &rag::answer::RagAnswer,
     output_file: &str,
     prompt: &str,
-    model: &str,
+    grounding: &str,
+    save_context: bool,
 ) {
     // Create the output file at the specified path
     let mut file = File::create(output_file)
@@ -379,31 +786,94 @@ async fn handle_success_local_rag(
 
     // Write markdown-formatted header information to the file
     writeln!(file, "# Local RAG Generation Result\n").unwrap();
-    writeln!(file, "## Model: {}\n", model).unwrap();
+    writeln!(file, "## Model: {} (local RAG)\n", answer.model).unwrap();
+    writeln!(file, "## Grounding\n\n{}\n", grounding).unwrap();
     writeln!(file, "## Prompt\n\n{}", prompt).unwrap();
 
     // Write each response to file with numbered sections
-    for (i, response) in responses.iter().enumerate() {
+    for i in 0..answer.answers.len() {
         // Write response to file with numbered sections
-        writeln!(file, "\n## Response {}\n{}", i + 1, response).unwrap();
+        writeln!(
+            file,
+            "\n## Response {}\n\n{}",
+            i + 1,
+            answer.render_markdown(i)
+        )
+        .unwrap();
+    }
+
+    // Append the retrieved text exactly as the model received it
+    if save_context {
+        writeln!(file, "\n{}", answer.render_context_markdown()).unwrap();
     }
 
     // Add a blank line at the end of the markdown file for proper formatting
-    writeln!(file, "").unwrap();
+    writeln!(file).unwrap();
 
     // Confirm successful file save to user
-    let msg = format!("Local RAG response saved to file: ")
+    let msg = "Local RAG response saved to file: "
+        .to_string()
         .bright_yellow()
         .bold();
     println!("\t {}: {}", msg, output_file.bright_green().bold());
 }
 
+// Handle a local RAG query that could not be answered in strict local mode
+// A query with no relevant material is answered "Not found in the documents." and the
+// answer is saved like any other, so the output folder keeps a record of it
+// Parameters:
+//   - error: Why the knowledge base could not answer
+//   - matches: Parsed command-line arguments (for the output file name)
+//   - prompt: Original prompt that was asked
+//   - kb_name: Name of the knowledge base that was searched
+fn handle_strict_local_failure(
+    error: &LocalRagError,
+    matches: &clap::ArgMatches,
+    prompt: &str,
+    kb_name: &str,
+) {
+    match error {
+        LocalRagError::NotFound(reason) => {
+            colour_print("\t Not found in the documents.", "red");
+
+            let output_file = matches.get_one::<String>("output").unwrap();
+            let output_file_with_path = generate_unique_output_path("0_out", output_file);
+            let mut file = File::create(&output_file_with_path)
+                .unwrap_or_else(|_| panic!("Failed to create file {}", output_file_with_path));
+            writeln!(file, "# Local RAG Generation Result\n").unwrap();
+            writeln!(
+                file,
+                "## Grounding\n\nNot found in the local knowledge base '{}' ({}); strict local mode, no fallback\n",
+                kb_name, reason
+            )
+            .unwrap();
+            writeln!(file, "## Prompt\n\n{}", prompt).unwrap();
+            writeln!(file, "\n## Response 1\nNot found in the documents.\n").unwrap();
+
+            let msg = "Local RAG response saved to file: "
+                .to_string()
+                .bright_yellow()
+                .bold();
+            println!(
+                "\t {}: {}",
+                msg,
+                output_file_with_path.bright_green().bold()
+            );
+        }
+        LocalRagError::Failed(reason) => {
+            colour_print(&format!("\t {}", reason), "red");
+            colour_print(
+                "\t Strict local mode: not falling back to the general model.",
+                "red",
+            );
+        }
+    }
+}
+
 // Handle failure case when response generation fails
 // Displays user-friendly error message with troubleshooting suggestions
 fn handle_failure() {
-    let error_msg = format!(
-        "\n\t Oh no, an error...\n\t Failed to generate response.\n\t Is the Ollama server running? \n\t Check that model has been pulled already?"
-    ).bright_red().bold();
+    let error_msg = "\n\t Oh no, an error...\n\t Failed to generate response.\n\t Is the Ollama server running? \n\t Check that model has been pulled already?".to_string().bright_red().bold();
 
     eprintln!("{}", error_msg);
 }
@@ -496,20 +966,81 @@ fn colour_print(text: &str, colour: &str) {
 
 // ==================== RAG SYSTEM HANDLER FUNCTIONS ====================
 
-// Handle RAG-specific commands (build, remove, status)
+// Handle RAG-specific commands (build, watch, remove, status, export, search, create, configure, list, delete)
 // Parameters:
-//   - command: The RAG command to execute ("build", "remove", or "status")
-async fn handle_rag_command(command: &str) {
-    // Initialize RAG system with agentic directory for model storage and data directory for source files
-    let mut rag_system = rag::RagSystem::new("agentic", "data");
+//   - args: The RAG command to execute, optionally followed by a knowledge base name (or a query for 'search')
+//   - matches: Parsed command-line arguments (for command options such as --full)
+async fn handle_rag_command(args: &[String], matches: &clap::ArgMatches) {
+    let command = args[0].as_str();
+
+    // 'search' takes a query rather than a knowledge base name, which then comes from --kb
+    if command == "search" {
+        match args.get(1) {
+            Some(query) => search_knowledge_base(query, matches),
+            None => colour_print(
+                "\t Please give a query, e.g. --rag search \"notice period\"",
+                "red",
+            ),
+        }
+        return;
+    }
+
+    // Knowledge base to work on: positional name, then --kb, then the built-in default
+    let kb_name = args
+        .get(1)
+        .or(matches.get_one::<String>("kb"))
+        .map(String::as_str)
+        .unwrap_or(rag::collections::DEFAULT_COLLECTION);
+
+    // Commands that manage the collection registry rather than a single index
+    match command {
+        "create" => {
+            create_collection(kb_name, matches);
+            return;
+        }
+        "configure" => {
+            configure_collection(kb_name, matches);
+            return;
+        }
+        "delete" => {
+            delete_collection(kb_name);
+            return;
+        }
+        "list" => {
+            list_collections();
+            return;
+        }
+        _ => {}
+    }
+
+    // Initialize RAG system with the knowledge base's index directory and data directories
+    let mut rag_system = match open_knowledge_base(kb_name) {
+        Ok(rag_system) => rag_system,
+        Err(e) => {
+            colour_print(&format!("\t {}", e), "red");
+            return;
+        }
+    };
+
+    // Bound the extraction worker pool if requested
+    if let Some(jobs) = matches.get_one::<u16>("jobs") {
+        rag_system.workers = usize::from(*jobs);
+    }
 
     match command {
-        // Build local knowledge base from documents in data/ directory
+        // Build (or incrementally update) local knowledge base from documents in its data directories
         "build" => {
-            if let Err(e) = rag_system.build_local_model() {
+            let full = matches.get_flag("full");
+            if let Err(e) = rag_system.build_local_model(full) {
                 colour_print(&format!("\t Error building local model: {}", e), "red");
             }
         }
+        // Keep the local knowledge base in sync with its data directories until interrupted
+        "watch" => {
+            if let Err(e) = rag_system.watch(Duration::from_secs(2)) {
+                colour_print(&format!("\t Error watching data directory: {}", e), "red");
+            }
+        }
         // Remove local knowledge base and all associated files
         "remove" => {
             if let Err(e) = rag_system.remove_local_model() {
@@ -520,167 +1051,888 @@ async fn handle_rag_command(command: &str) {
         "status" => {
             if rag_system.is_model_available() {
                 // Attempt to load the model and display statistics
-                if let Ok(true) = rag_system.load_model() {
-                    let (doc_count, chunk_count) = rag_system.get_stats();
-                    colour_print(
+                match rag_system.load_model() {
+                    Ok(true) => {
+                        let (doc_count, chunk_count) = rag_system.get_stats();
+                        colour_print(
+                            &format!(
+                                "\t Local model is available with {} documents and {} chunks",
+                                doc_count, chunk_count
+                            ),
+                            "green",
+                        );
+                        let settings = &rag_system.settings;
+                        colour_print(
+                            &format!(
+                                "\t Index format v{}, analyzer '{}', {} chunking, chunk size {} {}, overlap {}",
+                                rag::storage::FORMAT_VERSION,
+                                settings.analyzer,
+                                settings.chunk_strategy,
+                                settings.chunk_size,
+                                settings.chunk_unit,
+                                settings.chunk_overlap
+                            ),
+                            "cyan",
+                        );
+                    }
+                    Ok(false) => colour_print("\t Local model exists but failed to load", "red"),
+                    Err(e) => colour_print(
                         &format!(
-                            "\t Local model is available with {} documents and {} chunks",
-                            doc_count, chunk_count
+                            "\t Local model cannot be used: {}. Please rebuild it with '{}'",
+                            e,
+                            build_command(kb_name, true)
                         ),
-                        "green",
-                    );
-                } else {
-                    colour_print("\t Local model exists but failed to load", "red");
+                        "red",
+                    ),
                 }
             } else {
                 colour_print(
-                    "\t No local model available. Use 'cargo run -- --rag build' to create one.",
+                    &format!(
+                        "\t No local model available. Use '{}' to create one.",
+                        build_command(kb_name, false)
+                    ),
                     "yellow",
                 );
             }
         }
+        // Export the local knowledge base as human-readable JSON files
+        "export" => match rag_system.load_model() {
+            Ok(true) => {
+                let export_dir = rag_system.agentic_dir.join("export");
+                match rag_system.export_json(&export_dir) {
+                    Ok(()) => colour_print(
+                        &format!(
+                            "\t Local model exported as JSON to {}",
+                            export_dir.display()
+                        ),
+                        "green",
+                    ),
+                    Err(e) => {
+                        colour_print(&format!("\t Error exporting local model: {}", e), "red")
+                    }
+                }
+            }
+            Ok(false) => colour_print(
+                &format!(
+                    "\t No local model available. Use '{}' to create one.",
+                    build_command(kb_name, false)
+                ),
+                "yellow",
+            ),
+            Err(e) => colour_print(
+                &format!(
+                    "\t Local model cannot be used: {}. Please rebuild it with '{}'",
+                    e,
+                    build_command(kb_name, true)
+                ),
+                "red",
+            ),
+        },
         // Handle invalid commands
         _ => {
             colour_print(
-                "\t Invalid RAG command. Use 'build', 'remove', or 'status'.",
+                "\t Invalid RAG command. Use 'build', 'watch', 'remove', 'status', 'export', 'search', 'create', 'configure', 'list', or 'delete'.",
                 "red",
             );
         }
     }
 }
 
-// Handle local RAG queries by searching the knowledge base and generating responses
+// Search a knowledge base without calling the model, printing the ranked chunks
 // Parameters:
-//   - query: The user's question/query to search for in the local knowledge base
-// Returns: Result containing formatted response or error message
-async fn handle_local_rag_query(query: &str) -> Result<String, String> {
-    // Initialize RAG system with standard directories
-    let mut rag_system = rag::RagSystem::new("agentic", "data");
+//   - query: The search query
+//   - matches: Parsed command-line arguments (for --kb, --top-k, --json and the filters)
+fn search_knowledge_base(query: &str, matches: &clap::ArgMatches) {
+    let kb_name = matches
+        .get_one::<String>("kb")
+        .map(String::as_str)
+        .unwrap_or(rag::collections::DEFAULT_COLLECTION);
+    let top_k = matches.get_one::<u16>("top_k").copied().unwrap_or(5);
+    let min_score = matches.get_one::<f32>("min_score").copied().unwrap_or(0.0);
+    let json = matches.get_flag("json");
+
+    let result = parse_filter(matches).and_then(|filter| {
+        let mut rag_system = open_knowledge_base(kb_name)?;
+        match rag_system.load_model() {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(format!(
+                    "No local model available. Use '{}' to create one.",
+                    build_command(kb_name, false)
+                ));
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Local model cannot be used: {}. Please rebuild it with '{}'",
+                    e,
+                    build_command(kb_name, true)
+                ));
+            }
+        }
+        rag_system
+            .search_hits(query, usize::from(top_k), min_score, &filter)
+            .map_err(|e| format!("Invalid search query: {}", e))
+    });
+
+    // With --json, stdout holds only the JSON, so errors go to stderr with a failing exit code
+    let hits = match result {
+        Ok(hits) => hits,
+        Err(e) if json => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            colour_print(&format!("\t {}", e), "red");
+            return;
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&hits) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                eprintln!("Error writing JSON: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if hits.is_empty() {
+        colour_print("\t No matching chunks found.", "yellow");
+        return;
+    }
+
+    for hit in &hits {
+        // "1. [0.1234] title, p. 4 > Install" followed by the source path and snippet
+        let location = if hit.location.is_empty() {
+            String::new()
+        } else {
+            format!(", {}", hit.location)
+        };
+        println!(
+            "\t {}. [{:.4}] {}{}",
+            hit.rank,
+            hit.score,
+            hit.title.bright_blue().bold(),
+            location.bright_cyan()
+        );
+        println!("\t    {}", hit.source.dimmed());
+
+        // Highlight the query terms in the snippet
+        let mut snippet = String::new();
+        let mut last = 0;
+        for &(start, end) in &hit.highlights {
+            snippet.push_str(&hit.snippet[last..start]);
+            snippet.push_str(&hit.snippet[start..end].bright_yellow().bold().to_string());
+            last = end;
+        }
+        snippet.push_str(&hit.snippet[last..]);
+        println!("\t    {}\n", snippet);
+    }
+}
+
+// Build the metadata filter from the --filter and --since options
+// Parameters:
+//   - matches: Parsed command-line arguments
+// Returns: Result containing the filter or a description of the malformed option
+fn parse_filter(matches: &clap::ArgMatches) -> Result<rag::metadata::MetadataFilter, String> {
+    let filters: Vec<String> = matches
+        .get_many::<String>("filter")
+        .map(|filters| filters.cloned().collect())
+        .unwrap_or_default();
+    let since = matches.get_one::<String>("since").map(String::as_str);
+    rag::metadata::MetadataFilter::parse(&filters, since).map_err(|e| e.to_string())
+}
+
+// Build the retrieval settings for a RAG query from the --top-k, --min-score,
+// --max-context-tokens, --expand, --mmr, --max-per-doc, --rerank and query rewriting options
+// Parameters:
+//   - matches: Parsed command-line arguments
+// Returns: Result containing the settings (with defaults for the options not given)
+//          or a description of an unreadable re-ranking prompt file
+fn retrieval_settings(
+    matches: &clap::ArgMatches,
+) -> Result<rag::context::RetrievalSettings, String> {
+    let defaults = rag::context::RetrievalSettings::default();
+
+    // Re-ranking rates the candidates with --rerank-model, or the answer model by default
+    let rerank = if matches.get_flag("rerank") {
+        let prompt = match matches.get_one::<String>("rerank_prompt") {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read re-ranking prompt {}: {}", path, e))?,
+            None => rag::rerank::DEFAULT_PROMPT.to_string(),
+        };
+        if !prompt.contains("{question}") || !prompt.contains("{passage}") {
+            return Err(
+                "The re-ranking prompt must contain {question} and {passage} placeholders"
+                    .to_string(),
+            );
+        }
+        Some(rag::rerank::RerankSettings {
+            model: matches
+                .get_one::<String>("rerank_model")
+                .or(matches.get_one::<String>("model"))
+                .unwrap()
+                .to_string(),
+            prompt,
+            candidates: matches
+                .get_one::<u16>("rerank_candidates")
+                .map_or(rag::rerank::DEFAULT_CANDIDATES, |&n| usize::from(n)),
+        })
+    } else {
+        None
+    };
+
+    // Query rewriting uses --rewrite-model, or the answer model by default
+    let paraphrases = matches
+        .get_one::<u16>("multi_query")
+        .map_or(0, |&n| usize::from(n));
+    let hyde = matches.get_flag("hyde");
+    let keywords = matches.get_flag("keywords");
+    let rewrite = (paraphrases > 0 || hyde || keywords).then(|| rag::rewrite::RewriteSettings {
+        paraphrases,
+        hyde,
+        keywords,
+        model: matches
+            .get_one::<String>("rewrite_model")
+            .or(matches.get_one::<String>("model"))
+            .unwrap()
+            .to_string(),
+    });
+
+    Ok(rag::context::RetrievalSettings {
+        top_k: matches
+            .get_one::<u16>("top_k")
+            .map_or(defaults.top_k, |&k| usize::from(k)),
+        min_score: matches
+            .get_one::<f32>("min_score")
+            .copied()
+            .unwrap_or(defaults.min_score),
+        max_context_tokens: matches
+            .get_one::<u32>("max_context_tokens")
+            .map_or(defaults.max_context_tokens, |&tokens| tokens as usize),
+        expand: matches
+            .get_one::<u16>("expand")
+            .map_or(defaults.expand, |&n| usize::from(n)),
+        mmr_lambda: matches.get_one::<f32>("mmr").copied(),
+        max_per_document: matches
+            .get_one::<u16>("max_per_doc")
+            .map(|&n| usize::from(n)),
+        rerank,
+        rewrite,
+        verbose: matches.get_flag("verbose"),
+        query_syntax: matches.get_flag("query_syntax"),
+    })
+}
+
+// Parse an option that must lie between 0 and 1 (--min-score, --mmr)
+// Parameters:
+//   - value: The option's value
+// Returns: Result containing the score or a description of the problem
+fn parse_fraction(value: &str) -> Result<f32, String> {
+    let score: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if !(0.0..=1.0).contains(&score) {
+        return Err(format!("{} is not between 0 and 1", score));
+    }
+    Ok(score)
+}
+
+// Command line that builds a knowledge base, for hints in messages
+// Parameters:
+//   - kb_name: Name of the collection ("default" needs no name on the command line)
+//   - full: Whether to add --full to discard the existing index
+// Returns: The command, e.g. "cargo run -- --rag build legal --full"
+fn build_command(kb_name: &str, full: bool) -> String {
+    let mut command = "cargo run -- --rag build".to_string();
+    if kb_name != rag::collections::DEFAULT_COLLECTION {
+        command.push(' ');
+        command.push_str(kb_name);
+    }
+    if full {
+        command.push_str(" --full");
+    }
+    command
+}
+
+// Open a knowledge base by name
+// Parameters:
+//   - kb_name: Name of the collection ("default" for data/ indexed into agentic/)
+// Returns: Result containing a RAG system configured for the collection or error message
+fn open_knowledge_base(kb_name: &str) -> Result<rag::RagSystem, String> {
+    let registry = rag::collections::Registry::load()
+        .map_err(|e| format!("Failed to read knowledge base registry: {}", e))?;
+    let collection = registry.get(kb_name).map_err(|e| e.to_string())?;
+    Ok(rag::RagSystem::from_collection(&collection))
+}
+
+// Register a new named knowledge base with its data directories
+// Parameters:
+//   - kb_name: Name of the new collection
+//   - matches: Parsed command-line arguments (for the --data directories)
+fn create_collection(kb_name: &str, matches: &clap::ArgMatches) {
+    let data: Vec<PathBuf> = matches
+        .get_many::<String>("data")
+        .map(|dirs| dirs.map(PathBuf::from).collect())
+        .unwrap_or_default();
+
+    let settings = chunk_settings(rag::storage::IndexSettings::default(), matches);
+    let result = rag::collections::Registry::load().and_then(|mut registry| {
+        let collection = registry.create(kb_name, data, settings)?;
+        registry.save()?;
+        Ok(collection)
+    });
+
+    match result {
+        Ok(collection) => colour_print(
+            &format!(
+                "\t Created knowledge base '{}' (index: {}). Use 'cargo run -- --rag build {}' to build it.",
+                kb_name,
+                collection.index_dir.display(),
+                kb_name
+            ),
+            "green",
+        ),
+        Err(e) => colour_print(&format!("\t Error creating knowledge base: {}", e), "red"),
+    }
+}
+
+// Change the chunking settings of a knowledge base
+// Parameters:
+//   - kb_name: Name of the collection ("default" for data/ indexed into agentic/)
+//   - matches: Parsed command-line arguments (for the --chunk-* options)
+fn configure_collection(kb_name: &str, matches: &clap::ArgMatches) {
+    let result = rag::collections::Registry::load().and_then(|mut registry| {
+        let settings = chunk_settings(registry.get(kb_name)?.settings, matches);
+        let collection = registry.configure(kb_name, settings)?;
+        registry.save()?;
+        Ok(collection)
+    });
+
+    match result {
+        Ok(collection) => {
+            let settings = &collection.settings;
+            colour_print(
+                &format!(
+                    "\t Knowledge base '{}' now uses {} chunking, chunk size {} {}, overlap {}",
+                    kb_name,
+                    settings.chunk_strategy,
+                    settings.chunk_size,
+                    settings.chunk_unit,
+                    settings.chunk_overlap
+                ),
+                "green",
+            );
+            colour_print(
+                &format!(
+                    "\t Run 'cargo run -- --rag build {}' to rebuild the index with the new settings.",
+                    kb_name
+                ),
+                "yellow",
+            );
+        }
+        Err(e) => colour_print(
+            &format!("\t Error configuring knowledge base: {}", e),
+            "red",
+        ),
+    }
+}
+
+// Apply the --chunk-* command-line options on top of existing index settings
+// Parameters:
+//   - settings: The settings to start from
+//   - matches: Parsed command-line arguments
+// Returns: The settings with any given options applied
+fn chunk_settings(
+    mut settings: rag::storage::IndexSettings,
+    matches: &clap::ArgMatches,
+) -> rag::storage::IndexSettings {
+    // Values are restricted by clap's value parsers, so parsing cannot fail here
+    if let Some(strategy) = matches.get_one::<String>("chunk_strategy")
+        && let Ok(strategy) = rag::chunking::ChunkStrategy::parse(strategy)
+    {
+        settings.chunk_strategy = strategy;
+    }
+    if let Some(unit) = matches.get_one::<String>("chunk_unit")
+        && let Ok(unit) = rag::chunking::ChunkUnit::parse(unit)
+    {
+        settings.chunk_unit = unit;
+    }
+    if let Some(size) = matches.get_one::<usize>("chunk_size") {
+        settings.chunk_size = *size;
+    }
+    if let Some(overlap) = matches.get_one::<usize>("chunk_overlap") {
+        settings.chunk_overlap = *overlap;
+    }
+    settings
+}
+
+// Unregister a named knowledge base and delete its index
+// Parameters:
+//   - kb_name: Name of the collection to delete
+fn delete_collection(kb_name: &str) {
+    let result = rag::collections::Registry::load().and_then(|mut registry| {
+        registry.delete(kb_name)?;
+        registry.save()
+    });
+
+    match result {
+        Ok(()) => colour_print(&format!("\t Deleted knowledge base '{}'", kb_name), "green"),
+        Err(e) => colour_print(&format!("\t Error deleting knowledge base: {}", e), "red"),
+    }
+}
+
+// List every knowledge base with its data directories and index statistics
+fn list_collections() {
+    let registry = match rag::collections::Registry::load() {
+        Ok(registry) => registry,
+        Err(e) => {
+            colour_print(
+                &format!("\t Failed to read knowledge base registry: {}", e),
+                "red",
+            );
+            return;
+        }
+    };
+
+    colour_print("\t Knowledge bases:", "yellow");
+    for (name, collection) in registry.all() {
+        // Read only the index header for the statistics, the payload is not needed
+        let index_path = collection.index_dir.join(rag::storage::INDEX_FILE);
+        let stats = if !index_path.exists() {
+            "not built".to_string()
+        } else {
+            match rag::storage::read_header(&index_path) {
+                Ok(header) => format!(
+                    "{} documents, {} chunks",
+                    header.document_count, header.chunk_count
+                ),
+                Err(e) => format!("unusable index ({})", e),
+            }
+        };
+
+        let data: Vec<String> = collection
+            .data
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect();
+
+        colour_print(&format!("\t   {} - {}", name, stats), "green");
+        colour_print(
+            &format!(
+                "\t     data: {}  index: {}",
+                data.join(", "),
+                collection.index_dir.display()
+            ),
+            "cyan",
+        );
+    }
+}
+
+// Why a local RAG query could not be answered from the knowledge base
+enum LocalRagError {
+    NotFound(String), // The knowledge base has no relevant material for the query
+    Failed(String),   // The knowledge base or the model could not be used
+}
+
+impl std::fmt::Display for LocalRagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalRagError::NotFound(reason) | LocalRagError::Failed(reason) => {
+                write!(f, "{}", reason)
+            }
+        }
+    }
+}
+
+// Open a knowledge base and load its index for local RAG queries
+// Parameters:
+//   - kb_name: Name of the knowledge base to search
+// Returns: Result containing the loaded RAG system or the reason it cannot be used
+fn open_local_model(kb_name: &str) -> Result<rag::RagSystem, LocalRagError> {
+    // Initialize RAG system with the knowledge base's directories
+    let mut rag_system = open_knowledge_base(kb_name).map_err(LocalRagError::Failed)?;
 
     // Check if a local model exists
     if !rag_system.is_model_available() {
-        return Err(
-            "No local model available. Use 'cargo run -- --rag build' to create one.".to_string(),
-        );
+        return Err(LocalRagError::Failed(format!(
+            "No local model available. Use '{}' to create one.",
+            build_command(kb_name, false)
+        )));
     }
 
     // Attempt to load the local model
     if let Err(e) = rag_system.load_model() {
-        return Err(format!("Failed to load local model: {}", e));
+        return Err(LocalRagError::Failed(format!(
+            "Failed to load local model: {}. Please rebuild it with '{}'",
+            e,
+            build_command(kb_name, true)
+        )));
     }
+    Ok(rag_system)
+}
 
+// Handle local RAG queries by searching the knowledge base and generating responses
+// Parameters:
+//   - rag_system: The knowledge base to search, with its index loaded
+//   - query: The user's question/query to search for in the local knowledge base
+//   - history: Earlier turns of a conversation to answer in the light of (empty for none)
+//   - filter: Metadata conditions restricting which documents are searched
+//   - settings: How many chunks to retrieve, the minimum relevance and the context budget
+//   - model: The Ollama model that writes the answer
+//   - num_results: The number of answers to generate from the same context
+// Returns: Result containing the answers and their sources or the reason no grounded answer was produced
+async fn handle_local_rag_query(
+    rag_system: &rag::RagSystem,
+    query: &str,
+    history: &str,
+    filter: &rag::metadata::MetadataFilter,
+    settings: &rag::context::RetrievalSettings,
+    model: &str,
+    num_results: usize,
+) -> Result<rag::answer::RagAnswer, LocalRagError> {
     // Inform user that local search is being performed
     colour_print("\t Searching local knowledge base...", "cyan");
 
-    // Search for relevant documents using TF-IDF scoring (top 3 results)
-    let search_results = rag_system.search_local(query, 3);
+    // Questions are natural language and are searched as plain words, unless --query-syntax
+    // asks for the query language; a malformed query (e.g. an unclosed quote) is then
+    // reported before any model is asked to rewrite it
+    let parsed = if settings.query_syntax {
+        rag::query::Query::parse(query)
+            .map_err(|e| LocalRagError::Failed(format!("Invalid search query: {}", e)))?
+    } else {
+        rag::query::Query::plain(query)
+    };
+
+    // Rewritten forms of the question to search for as well, if enabled
+    let queries = match &settings.rewrite {
+        Some(rewrite) => {
+            let queries = rag::rewrite::expand_query(query, rewrite).await;
+            if settings.verbose {
+                colour_print("\t Search queries:", "cyan");
+                for expanded in &queries {
+                    colour_print(
+                        &format!("\t   [{}] {}", expanded.kind, expanded.text),
+                        "cyan",
+                    );
+                }
+            }
+            Some(queries)
+        }
+        None => {
+            if settings.verbose {
+                colour_print(&format!("\t Search query: {}", query), "cyan");
+            }
+            None
+        }
+    };
+
+    // Search for relevant documents using TF-IDF scoring (top_k results, or a larger pool
+    // of candidates to pick from when diversity is enabled); chunks below the minimum
+    // relevance are left out before the results are cut to that number
+    let search = |min_score: f32| match &queries {
+        Some(queries) => rag_system.search_expanded(
+            queries,
+            &parsed,
+            settings.candidate_count(),
+            min_score,
+            filter,
+        ),
+        None => rag_system.search_query(&parsed, settings.candidate_count(), min_score, filter),
+    };
+    let mut search_results = search(settings.min_score);
 
     // Check if any relevant documents were found
     if search_results.is_empty() {
+        // Tell chunks below the minimum relevance threshold apart from no match at all
+        let best_score = search(0.0)
+            .iter()
+            .map(|(score, _)| *score)
+            .fold(0.0, f32::max);
+        if best_score > 0.0 {
+            colour_print(
+                &format!(
+                    "\t No document is relevant enough (best relevance {:.2}, minimum {:.2}).",
+                    best_score, settings.min_score
+                ),
+                "yellow",
+            );
+            colour_print(
+                "\t The request is outside the local model's capabilities.",
+                "yellow",
+            );
+            return Err(LocalRagError::NotFound(
+                "Query outside local model capabilities".to_string(),
+            ));
+        }
         colour_print("\t No relevant information found in local model.", "yellow");
         colour_print(
             "\t The request is outside the local model's capabilities.",
             "yellow",
         );
-        colour_print("\t Falling back to Ollama model...", "cyan");
-        return Err("No relevant local information found".to_string());
+        return Err(LocalRagError::NotFound(
+            "No relevant local information found".to_string(),
+        ));
     }
 
-    // Check if the best match meets minimum relevance threshold
-    let best_score = search_results
-        .first()
-        .map(|(score, _)| *score)
-        .unwrap_or(0.0);
-    if best_score < 0.001 {
-        // Minimum threshold for relevance
+    // Re-rank the candidates with the rating model, keeping the lexical order if it fails
+    if let Some(rerank) = &settings.rerank {
+        match rag_system.rerank(query, &search_results, rerank).await {
+            Ok(reranked) => search_results = reranked,
+            Err(e) => colour_print(&format!("\t Re-ranking skipped, {}", e), "yellow"),
+        }
+    }
+
+    // Pick the final top_k chunks, spreading them over documents if requested
+    let search_results = rag::diversity::select(search_results, settings);
+
+    // Keep as much of the most relevant chunks as fits the context budget
+    let (context_chunks, cut) = rag_system.build_context(query, &search_results, settings);
+    if cut > 0 {
         colour_print(
-            "\t The request is outside the local model's capabilities.",
+            &format!(
+                "\t Context trimmed to {} tokens: {} retrieved source(s) shortened or left out.",
+                settings.max_context_tokens, cut
+            ),
             "yellow",
         );
-        colour_print("\t Falling back to Ollama model...", "cyan");
-        return Err("Query outside local model capabilities".to_string());
     }
 
     // Generate context from the most relevant search results
-    let context = generate_context_from_search_results(&search_results, &rag_system);
+    let context = generate_context_from_search_results(&context_chunks, rag_system);
+
+    // In a conversation, the earlier turns come first so the answer can build on them
+    let history = if history.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "Conversation so far (for reference only; answer from the context):\n{}\n",
+            history
+        )
+    };
 
-    // Create enhanced prompt that includes local context
+    // Create enhanced prompt that includes local context and asks for numbered citations
     let enhanced_prompt = format!(
-        "Based on the following context from local documents, please answer the question:\n\nContext:\n{}\n\nQuestion: {}\n\nAnswer:",
-        context, query
+        "Based on the following context from local documents, please answer the question. \
+         The context is split into numbered sources. After each statement, cite the sources \
+         that support it by their numbers in square brackets, e.g. [1] or [2][3]. \
+         Only cite the numbered sources given below.\n\n{}Context:\n{}\n\nQuestion: {}\n\nAnswer:",
+        history, context, query
     );
 
-    // Use Ollama to generate a response using the enhanced prompt with local context
+    // Use Ollama to generate the requested number of responses from the same local context
     let ollama = Ollama::new("http://localhost".to_string(), 11434);
-    let model = "llama3.2".to_string();
+    let mut answers = Vec::new();
+    for _ in 0..num_results {
+        match ollama
+            .generate(GenerationRequest::new(
+                model.to_string(),
+                enhanced_prompt.clone(),
+            ))
+            .await
+        {
+            Ok(response) => answers.push(response.response),
+            Err(e) => {
+                return Err(LocalRagError::Failed(format!(
+                    "Failed to generate response with model '{}': {}",
+                    model, e
+                )));
+            }
+        }
+    }
+    colour_print(
+        &format!(
+            "\t Response generated by {} using local knowledge base:",
+            model
+        ),
+        "green",
+    );
+
+    // Keep the answers together with the sources they were grounded in
+    Ok(rag::answer::RagAnswer {
+        model: model.to_string(),
+        answers,
+        sources: rag_system.cite_sources(query, &context_chunks),
+        context,
+    })
+}
 
-    match ollama
-        .generate(GenerationRequest::new(model, enhanced_prompt))
+// Hold a conversation with the local knowledge base, one question at a time
+// Each follow-up is rewritten into a standalone question before retrieval, then answered
+// from the fresh context together with the earlier turns. The transcript, with the sources
+// of every answer, is saved after each turn so an interrupted session is not lost
+// Parameters:
+//   - kb_name: Name of the knowledge base to search
+//   - filter: Metadata conditions restricting which documents are searched
+//   - settings: How many chunks to retrieve, the minimum relevance and the context budget
+//   - model: The Ollama model that writes the answers
+//   - matches: Parsed command-line arguments (for the first question, --rewrite-model and --output)
+async fn handle_local_rag_chat(
+    kb_name: &str,
+    filter: &rag::metadata::MetadataFilter,
+    settings: &rag::context::RetrievalSettings,
+    model: &str,
+    matches: &clap::ArgMatches,
+) {
+    let rag_system = match open_local_model(kb_name) {
+        Ok(rag_system) => rag_system,
+        Err(e) => {
+            colour_print(&format!("\t {}", e), "red");
+            return;
+        }
+    };
+
+    // Follow-up questions are rewritten by --rewrite-model, or the answer model by default
+    let condense_model = matches
+        .get_one::<String>("rewrite_model")
+        .map_or(model, String::as_str);
+    let output_file = matches.get_one::<String>("output").unwrap();
+    let output_file_with_path = generate_unique_output_path("0_out", output_file);
+    let grounding = format!("Grounded in the local knowledge base '{}'", kb_name);
+
+    colour_print(
+        &format!(
+            "\t Chatting with the knowledge base '{}'. Enter an empty line or 'exit' to finish.",
+            kb_name
+        ),
+        "cyan",
+    );
+
+    // A --prompt given on the command line opens the conversation
+    let mut first_question = matches.get_one::<String>("prompt").cloned();
+    let mut conversation = rag::conversation::Conversation::default();
+    loop {
+        let question = match first_question.take() {
+            Some(question) => question,
+            None => match get_input("\n\t You : ") {
+                Ok(question) => question,
+                Err(_) => break,
+            },
+        };
+        if question.is_empty() || matches!(question.to_lowercase().as_str(), "exit" | "quit") {
+            break;
+        }
+
+        // Rewrite a follow-up such as "what about its weight?" so it can be searched alone
+        let query = match conversation.condense(&question, condense_model).await {
+            Ok(query) => query,
+            Err(e) => {
+                colour_print(&format!("\t Follow-up not rewritten, {}", e), "yellow");
+                question.clone()
+            }
+        };
+        if query != question {
+            colour_print(&format!("\t Standalone question: {}", query), "cyan");
+        }
+
+        // A question rewritten by the model is always searched as plain words
+        let turn_settings = rag::context::RetrievalSettings {
+            query_syntax: settings.query_syntax && query == question,
+            ..settings.clone()
+        };
+
+        // Answer from the fresh context, with the earlier turns for reference
+        let history = conversation.history();
+        let answer = match handle_local_rag_query(
+            &rag_system,
+            &query,
+            &history,
+            filter,
+            &turn_settings,
+            model,
+            1,
+        )
         .await
-    {
-        Ok(response) => {
-            // Format successful response with source attribution
-            colour_print("\t Response generated using local knowledge base:", "green");
-            let formatted_response = format!(
-                "\n\t📚 **Local Knowledge Base Response:**\n\t{}\n\n\t**Sources used:**\n{}",
-                response.response.bright_cyan(),
-                format_sources(&search_results, &rag_system)
+        {
+            Ok(answer) => {
+                println!("{}", answer.render_terminal(0));
+                Some(answer)
+            }
+            Err(LocalRagError::NotFound(_)) => {
+                colour_print("\t Not found in the documents.", "yellow");
+                None
+            }
+            Err(e) => {
+                colour_print(&format!("\t {}", e), "red");
+                continue;
+            }
+        };
+        conversation.turns.push(rag::conversation::Turn {
+            question,
+            query,
+            answer,
+        });
+
+        // Save the whole transcript so far
+        if let Err(e) = std::fs::write(
+            &output_file_with_path,
+            conversation.render_markdown(model, &grounding),
+        ) {
+            colour_print(
+                &format!("\t Failed to save {}: {}", output_file_with_path, e),
+                "red",
             );
-            Ok(formatted_response)
         }
-        Err(e) => Err(format!("Failed to generate response: {}", e)),
     }
+
+    if conversation.turns.is_empty() {
+        colour_print(
+            "\t No questions were answered, so no transcript was saved.",
+            "yellow",
+        );
+        return;
+    }
+    let msg = "Conversation transcript saved to file: "
+        .to_string()
+        .bright_yellow()
+        .bold();
+    println!(
+        "\t {}: {}",
+        msg,
+        output_file_with_path.bright_green().bold()
+    );
 }
 
 // Generate context string from search results for use in enhanced prompts
 // Parameters:
-//   - search_results: Vector of (score, chunk) tuples from the search
+//   - context_chunks: Retrieved chunks that fit the context budget, best first
 //   - rag_system: Reference to the RAG system for document lookup
 // Returns: Formatted context string containing relevant document excerpts
 fn generate_context_from_search_results(
-    search_results: &[(f32, &rag::DocumentChunk)],
+    context_chunks: &[rag::context::ContextChunk],
     rag_system: &rag::RagSystem,
 ) -> String {
     let mut context = String::new();
 
-    // Process up to 3 most relevant search results
-    for (score, chunk) in search_results.iter().take(3) {
+    // Process the chunks selected for the context, numbering them [1], [2], ... for citation
+    // (the numbers match the reference list of the answer, so every chunk keeps its number)
+    for (i, context_chunk) in context_chunks.iter().enumerate() {
+        let chunk = context_chunk.chunk;
+
         // Get the document title for each chunk
-        if let Some(doc) = rag_system.get_document_by_id(&chunk.document_id) {
-            context.push_str(&format!(
-                "Document: {}\nRelevance Score: {:.4}\nContent: {}\n\n",
-                doc.title, score, chunk.content
-            ));
+        let title = rag_system
+            .get_document_by_id(&chunk.document_id)
+            .map_or("Unknown document", |doc| doc.title.as_str());
+        // Cite PDF chunks as "title, p. 42–43" (covering any neighbours added to the hit)
+        match context_chunk.page_label() {
+            Some(pages) => {
+                context.push_str(&format!("[{}] Document: {}, {}\n", i + 1, title, pages))
+            }
+            None => context.push_str(&format!("[{}] Document: {}\n", i + 1, title)),
         }
-    }
-
-    context
-}
-
-// Format source attribution for display to user
-// Parameters:
-//   - search_results: Vector of (score, chunk) tuples from the search
-//   - rag_system: Reference to the RAG system for document lookup
-// Returns: Formatted string showing sources and their relevance scores
-fn format_sources(
-    search_results: &[(f32, &rag::DocumentChunk)],
-    rag_system: &rag::RagSystem,
-) -> String {
-    let mut sources = String::new();
-
-    // Format up to 3 sources with numbering and relevance scores
-    for (i, (score, chunk)) in search_results.iter().take(3).enumerate() {
-        if let Some(doc) = rag_system.get_document_by_id(&chunk.document_id) {
-            sources.push_str(&format!(
-                "\t  {}. {} (Relevance: {:.4})\n",
-                i + 1,
-                doc.title.bright_blue(),
-                score
-            ));
+        // Include the section the chunk comes from for structured documents
+        if !chunk.heading_path.is_empty() {
+            context.push_str(&format!("Section: {}\n", chunk.heading_path));
         }
+        context.push_str(&format!(
+            "Relevance Score: {:.4}\nContent: {}\n\n",
+            context_chunk.score, context_chunk.content
+        ));
     }
 
-    sources
+    context
 }
 
 // ==================== FILE HANDLING FUNCTIONS ====================
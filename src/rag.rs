@@ -1,36 +1,66 @@
 // RAG (Retrieval-Augmented Generation) System Module
 // This module implements a local document processing and search system that can:
-// 1. Extract text from PDF, TXT, and MD files
+// 1. Extract text from PDF, TXT, MD, DOCX, ODT, HTML, EPUB and RTF files
 // 2. Split documents into searchable chunks
 // 3. Build a TF-IDF based search index
 // 4. Perform semantic search on the local knowledge base
 // 5. Unable to make a cup of tea, but can help you find information about it!
 
 use crate::colour_print;
-use anyhow::Result;
-use pdf_extract::extract_text;
+use anyhow::{Result, anyhow, bail};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
+use std::io::BufWriter;
+use std::num::NonZeroUsize;
+use std::panic;
 use std::path::{Path, PathBuf};
-use uuid::Uuid;
+use std::thread;
 use walkdir::WalkDir;
 
+pub mod answer; // Structured result of a RAG query, rendered for the terminal or Markdown
+pub mod chunking; // Structure-aware chunking for Markdown documents
+pub mod collections; // Named knowledge bases with their own data roots and settings
+pub mod context; // Retrieval settings and the context budget for RAG queries
+pub mod conversation; // Chat history and follow-up question condensation for conversational RAG
+pub mod diversity; // Diversity-aware selection of the final chunks (MMR and a per-document cap)
+pub mod extractors; // Text extraction for DOCX, ODT, HTML, EPUB and RTF documents
+pub mod manifest; // Source file manifest for incremental builds
+pub mod metadata; // Document metadata collected at ingestion and metadata filters for search
+pub mod pages; // Page ranges of chunks from paged documents (PDF)
+pub mod query; // Query language: phrases, required and excluded terms, OR groups and fields
+pub mod reply; // Cleaning model replies: reasoning of thinking models and citation markers
+pub mod report; // Per-file build report (successes, skips, warnings and failures)
+pub mod rerank; // Optional re-ranking of retrieved candidates by an Ollama model
+pub mod rewrite; // Query rewriting: paraphrases, hypothetical answers (HyDE) and keywords
 pub mod search; // TF-IDF search implementation
+pub mod storage; // Binary on-disk index format with versioned header
 pub mod tokenizer; // Text tokenization utilities (currently placeholder)
+pub mod watch; // Filesystem watcher that keeps the index in sync with the data directory
+
+use chunking::{TextChunk, TextFormat};
+use collections::Collection;
+use manifest::{FileStatus, Manifest, SourceFile};
+use metadata::MetadataFilter;
+use query::{Occur, Query, Term};
+use report::BuildReport;
+use storage::IndexSettings;
 
 // Data structure representing a complete document in the knowledge base
 // Each document maintains metadata and is linked to its constituent chunks
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
-    pub id: String,                        // Unique identifier for the document
-    pub title: String,                     // Document title (usually filename)
-    pub content: String,                   // Full text content of the document
-    pub source: String,                    // Original file path or source location
-    pub chunk_index: usize,                // Index position in the document collection
-    pub metadata: HashMap<String, String>, // Additional metadata (file type, size, etc.)
+    pub id: String,                         // Unique identifier for the document
+    pub title: String,                      // Document title (usually filename)
+    pub content: String,                    // Full text content of the document
+    pub source: String,                     // Original file path or source location
+    pub chunk_index: usize,                 // Index position in the document collection
+    pub metadata: BTreeMap<String, String>, // Additional metadata (file type, size, etc.)
 }
 
 // Data structure representing a chunk (segment) of a document
@@ -42,6 +72,50 @@ pub struct DocumentChunk {
     pub content: String,     // Text content of this chunk
     pub chunk_index: usize,  // Position of this chunk within the parent document
     pub word_count: usize,   // Number of words in this chunk
+    #[serde(default)]
+    pub heading_path: String, // Markdown headings above this chunk ("Install > Linux"), if any
+    #[serde(default)]
+    pub pages: Option<(usize, usize)>, // First and last PDF page (1-based) the chunk comes from
+}
+
+impl DocumentChunk {
+    /// Describe the pages a chunk comes from, e.g. "p. 42" or "p. 42–43"
+    /// Returns: The page label, or None for documents without pages
+    pub fn page_label(&self) -> Option<String> {
+        match self.pages? {
+            (first, last) if first == last => Some(format!("p. {}", first)),
+            (first, last) => Some(format!("p. {}–{}", first, last)),
+        }
+    }
+}
+
+// Occurrences of a word in one chunk, as stored in the word index
+// The positions let phrase queries check that words appear one after another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Posting {
+    pub chunk: usize,        // Index of the chunk in RagSystem::chunks
+    pub positions: Vec<u32>, // Positions of the word among the chunk's words, ascending
+}
+
+// Action taken for a source file when bringing the index up to date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncAction {
+    Added,     // New file was extracted and indexed
+    Changed,   // Modified file was re-extracted
+    Removed,   // Deleted file had its chunks dropped
+    Unchanged, // File matches the manifest, nothing to do
+}
+
+// A document extracted from a source file, with its chunks, ready to be added to the index
+struct Extracted {
+    document: Document,
+    chunks: Vec<DocumentChunk>,
+}
+
+// What has to happen to the index for one source file, worked out without modifying it
+enum FileUpdate {
+    Unchanged,                                         // File matches the manifest
+    Extracted(SyncAction, SourceFile, Box<Extracted>), // New or changed file, already extracted
 }
 
 // Main RAG system structure that manages the local knowledge base
@@ -49,19 +123,22 @@ pub struct RagSystem {
     pub documents: Vec<Document>,   // Collection of all processed documents
     pub chunks: Vec<DocumentChunk>, // Collection of all document chunks
     pub agentic_dir: PathBuf,       // Directory for storing model files
-    pub data_dir: PathBuf,          // Directory containing source documents
-    pub word_index: HashMap<String, Vec<usize>>, // TF-IDF word index: word -> chunk indices
+    pub data_dirs: Vec<PathBuf>,    // Directories containing source documents
+    pub word_index: HashMap<String, Vec<Posting>>, // Positional word index: word -> chunks and positions
+    pub settings: IndexSettings, // Analyzer/chunker settings stored in the index header
+    pub workers: usize,          // Number of threads used to extract documents during a build
 }
 
 impl RagSystem {
     /// Create a new RAG system instance
     /// Parameters:
     ///   - agentic_dir: Directory path for storing processed model files
-    ///   - data_dir: Directory path containing source documents to process
+    ///   - data_dirs: Directory paths containing source documents to process
+    ///   - settings: Analyzer/chunker settings to build (and expect) the index with
+    ///
     /// Returns: New RagSystem instance with empty collections
-    pub fn new(agentic_dir: &str, data_dir: &str) -> Self {
-        let agentic_path = PathBuf::from(agentic_dir);
-        let data_path = PathBuf::from(data_dir);
+    pub fn new(agentic_dir: &Path, data_dirs: Vec<PathBuf>, settings: IndexSettings) -> Self {
+        let agentic_path = agentic_dir.to_path_buf();
 
         // Ensure agentic directory exists - create if necessary
         if !agentic_path.exists() {
@@ -72,61 +149,181 @@ impl RagSystem {
             documents: Vec::new(),
             chunks: Vec::new(),
             agentic_dir: agentic_path,
-            data_dir: data_path,
+            data_dirs,
             word_index: HashMap::new(),
+            settings,
+            workers: thread::available_parallelism()
+                .map(NonZeroUsize::get)
+                .unwrap_or(1),
         }
     }
 
+    /// Create a RAG system instance for a named knowledge base
+    /// Parameters:
+    ///   - collection: Collection configuration (data roots, index directory, settings)
+    ///
+    /// Returns: New RagSystem instance with empty collections
+    pub fn from_collection(collection: &Collection) -> Self {
+        Self::new(
+            &collection.index_dir,
+            collection.data.clone(),
+            collection.settings.clone(),
+        )
+    }
+
     /// Build the local RAG model by processing all documents in the data directory
     /// This method:
-    /// 1. Scans the data directory for supported file types (PDF, TXT, MD)
-    /// 2. Extracts text content from each file
-    /// 3. Splits documents into searchable chunks
-    /// 4. Builds a TF-IDF search index
-    /// 5. Saves the processed model to disk
+    /// 1. Scans the data directories for supported file types (PDF, TXT, MD, DOCX, ODT, HTML, EPUB, RTF)
+    /// 2. Compares each file against the manifest of previously indexed files
+    /// 3. Extracts and chunks only new or changed files (or every file on a full rebuild),
+    ///    in parallel on `workers` threads, merging the results in file path order
+    /// 4. Drops the chunks of files that were deleted from the data directory
+    /// 5. Builds a TF-IDF search index
+    /// 6. Saves the processed model and manifest to disk
+    ///
+    /// Parameters:
+    ///   - full: Discard the existing index and re-extract every file
+    ///
     /// Returns: Result indicating success or failure
-    pub fn build_local_model(&mut self) -> Result<()> {
+    pub fn build_local_model(&mut self, full: bool) -> Result<()> {
         colour_print("\t Building local RAG model from documents...", "cyan");
 
-        // Clear any existing data before rebuilding
-        self.documents.clear();
-        self.chunks.clear();
+        let manifest_path = self.agentic_dir.join(manifest::MANIFEST_FILE);
+
+        // Start from the existing index unless a clean rebuild was requested
+        // (or the existing index cannot be used with the current format and settings)
+        let reuse_existing = !full
+            && match self.load_model() {
+                Ok(loaded) => loaded,
+                Err(_) => {
+                    colour_print(
+                        "\t Existing index is stale or incompatible, rebuilding from scratch",
+                        "yellow",
+                    );
+                    false
+                }
+            };
+        let mut manifest = if reuse_existing {
+            Manifest::load(&manifest_path)?
+        } else {
+            if full {
+                colour_print(
+                    "\t Full rebuild requested, discarding existing index",
+                    "yellow",
+                );
+            }
+            self.documents.clear();
+            self.chunks.clear();
+            Manifest::default()
+        };
         self.word_index.clear();
 
-        // Process all files in the data directory recursively
-        for entry in WalkDir::new(&self.data_dir) {
-            let entry = entry?;
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut report = BuildReport::default();
+        let mut files: Vec<PathBuf> = Vec::new();
+
+        // Collect all files in the data directories recursively, sorted by name so the
+        // index is built in the same order on every machine
+        // A file or directory that cannot be read is reported and the build carries on
+        for entry in self
+            .data_dirs
+            .iter()
+            .flat_map(|dir| WalkDir::new(dir).sort_by_file_name())
+        {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().map(Path::to_path_buf).unwrap_or_default();
+                    report.fail(&path, format!("cannot read directory entry: {}", e));
+                    continue;
+                }
+            };
             let path = entry.path();
 
-            // Only process files (skip directories)
-            if path.is_file() {
-                // Check file extension to determine processing method
-                match path.extension().and_then(|s| s.to_str()) {
-                    Some("pdf") => {
-                        colour_print(&format!("\t Processing PDF: {}", path.display()), "yellow");
-                        self.process_pdf(path)?;
-                    }
-                    Some("txt") | Some("md") => {
-                        colour_print(
-                            &format!("\t Processing text file: {}", path.display()),
-                            "yellow",
-                        );
-                        self.process_text_file(path)?;
-                    }
-                    _ => {
-                        // Skip unsupported file types silently
-                        continue;
-                    }
+            // Only process files (skip directories), and note unsupported file types
+            if !path.is_file() {
+                continue;
+            }
+            if !Self::is_supported(path) {
+                let reason = match path.extension().and_then(|s| s.to_str()) {
+                    Some(ext) => format!("unsupported file type '.{}'", ext),
+                    None => "no file extension".to_string(),
+                };
+                report.skip(path, reason);
+                continue;
+            }
+
+            seen.insert(path.to_string_lossy().to_string());
+            files.push(path.to_path_buf());
+        }
+
+        // Only re-extract files whose contents differ from the manifest
+        // Files are checked and extracted in parallel; the results come back in file order
+        let progress = Self::progress_bar(files.len());
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.workers)
+            .build()?;
+        let updates: Vec<Result<FileUpdate>> = pool.install(|| {
+            files
+                .par_iter()
+                .map(|path| {
+                    let update = self.prepare_file(path, &manifest);
+                    progress.set_message(path.display().to_string());
+                    progress.inc(1);
+                    update
+                })
+                .collect()
+        });
+        progress.finish_and_clear();
+
+        // Merge the results into the index one file at a time, in file order
+        for (path, update) in files.iter().zip(updates) {
+            let source = path.to_string_lossy().to_string();
+            match update.and_then(|update| self.apply_update(path, update, &mut manifest)) {
+                Ok(SyncAction::Added) => report.added.push(source.clone()),
+                Ok(SyncAction::Changed) => report.changed.push(source.clone()),
+                Ok(SyncAction::Unchanged | SyncAction::Removed) => report.unchanged += 1,
+                Err(e) => {
+                    // Drop any stale copy of the document; the file is retried next build
+                    self.remove_source(&source);
+                    report.fail(path, e.to_string());
+                    continue;
                 }
             }
+
+            // Warn about documents that came out empty (reported on every build until fixed)
+            if let Some(warning) = self.extraction_warning(&source) {
+                report.warn(path, warning);
+            }
+        }
+
+        // Drop documents (and their chunks) whose source file no longer exists
+        let deleted: Vec<String> = self
+            .documents
+            .iter()
+            .filter(|doc| !seen.contains(&doc.source))
+            .map(|doc| doc.source.clone())
+            .collect();
+        for source in &deleted {
+            colour_print(&format!("\t Removing deleted file: {}", source), "yellow");
+            self.remove_source(source);
         }
+        manifest.files.retain(|path, _| seen.contains(path));
+        report.removed = deleted;
 
         // Build TF-IDF word index for efficient searching
         colour_print("\t Building search index...", "cyan");
+        self.sort_by_source();
         self.build_word_index();
 
-        // Persist the processed model to disk
+        // Persist the processed model, manifest and build report to disk
         self.save_model()?;
+        manifest.save(&manifest_path)?;
+        report.finish(self.documents.len(), self.chunks.len());
+        report.save(&self.agentic_dir.join(report::REPORT_FILE))?;
+
+        // Report what happened to each file, including those that were not indexed and why
+        report.print();
 
         colour_print(
             &format!(
@@ -140,13 +337,250 @@ impl RagSystem {
         Ok(())
     }
 
+    /// Bring the index up to date with a single source file
+    /// New or changed files are (re-)extracted, missing files have their chunks dropped
+    /// Parameters:
+    ///   - path: Path to the source file (as found under the data directory)
+    ///   - manifest: Manifest of indexed files, updated in place
+    ///
+    /// Returns: The action that was taken for this file
+    fn sync_file(&mut self, path: &Path, manifest: &mut Manifest) -> Result<SyncAction> {
+        let source = path.to_string_lossy().to_string();
+
+        // A path that no longer exists (file or whole directory) has been deleted
+        if !path.exists() {
+            let removed = self.remove_sources_under(path);
+            manifest
+                .files
+                .retain(|file, _| !Path::new(file).starts_with(path));
+            return Ok(if removed {
+                SyncAction::Removed
+            } else {
+                SyncAction::Unchanged
+            });
+        }
+
+        match self.prepare_file(path, manifest) {
+            Ok(update) => self.apply_update(path, update, manifest),
+            Err(e) => {
+                // Drop any stale copy of the document; the file is retried next time
+                self.remove_source(&source);
+                Err(e)
+            }
+        }
+    }
+
+    /// Compare a file with the manifest and extract it if it is new or changed
+    /// Only reads the index, so several files can be prepared in parallel
+    /// Parameters:
+    ///   - path: Path to the source file
+    ///   - manifest: Manifest of indexed files
+    ///
+    /// Returns: The update to apply to the index for this file
+    fn prepare_file(&self, path: &Path, manifest: &Manifest) -> Result<FileUpdate> {
+        match manifest.check(path)? {
+            FileStatus::Unchanged => Ok(FileUpdate::Unchanged),
+            FileStatus::New(file) => {
+                let extracted = self.extract_file(path, &file)?;
+                Ok(FileUpdate::Extracted(
+                    SyncAction::Added,
+                    file,
+                    Box::new(extracted),
+                ))
+            }
+            FileStatus::Changed(file) => {
+                let extracted = self.extract_file(path, &file)?;
+                Ok(FileUpdate::Extracted(
+                    SyncAction::Changed,
+                    file,
+                    Box::new(extracted),
+                ))
+            }
+        }
+    }
+
+    /// Apply a prepared update to the index and the manifest
+    /// Parameters:
+    ///   - path: Path to the source file
+    ///   - update: The update returned by prepare_file
+    ///   - manifest: Manifest of indexed files, updated in place
+    ///
+    /// Returns: The action that was taken
+    fn apply_update(
+        &mut self,
+        path: &Path,
+        update: FileUpdate,
+        manifest: &mut Manifest,
+    ) -> Result<SyncAction> {
+        match update {
+            FileUpdate::Unchanged => {
+                manifest.touch(path)?;
+                Ok(SyncAction::Unchanged)
+            }
+            FileUpdate::Extracted(action, file, extracted) => {
+                self.remove_source(&extracted.document.source);
+                self.documents.push(extracted.document);
+                self.chunks.extend(extracted.chunks);
+                manifest.insert(file);
+                Ok(action)
+            }
+        }
+    }
+
+    /// Create the progress bar shown while files are extracted
+    /// Parameters:
+    ///   - files: Number of files to process
+    ///
+    /// Returns: A progress bar showing files/sec and the estimated time remaining
+    fn progress_bar(files: usize) -> ProgressBar {
+        let progress = ProgressBar::new(files as u64);
+        if let Ok(style) = ProgressStyle::with_template(
+            "\t {bar:40.cyan/blue} {pos}/{len} files ({rate}, ETA {eta}) {wide_msg}",
+        ) {
+            let style = style
+                .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| {
+                    let _ = write!(w, "{:.1} files/s", state.per_sec());
+                })
+                .progress_chars("=> ");
+            progress.set_style(style);
+        }
+        progress
+    }
+
+    /// Check an indexed document for signs that extraction did not really work
+    /// Parameters:
+    ///   - source: Source path of the document
+    ///
+    /// Returns: A warning message, or None if the document looks fine
+    fn extraction_warning(&self, source: &str) -> Option<String> {
+        let doc = self.documents.iter().find(|doc| doc.source == source)?;
+        if let Some(pages) = doc.metadata.get("unreadable_pages") {
+            return Some(format!("unreadable PDF pages skipped: {}", pages));
+        }
+        if !doc.content.trim().is_empty() {
+            return None;
+        }
+
+        let warning = match doc.metadata.get("type").map(String::as_str) {
+            Some("pdf") => "PDF yielded no text — probably scanned",
+            _ => "no text could be extracted",
+        };
+        Some(warning.to_string())
+    }
+
+    /// Check whether a file has an extension the build knows how to process
+    /// Parameters:
+    ///   - path: Path to the candidate file
+    ///
+    /// Returns: true for PDF, TXT, MD, DOCX, ODT, HTML, EPUB and RTF files
+    fn is_supported(path: &Path) -> bool {
+        matches!(
+            Self::extension(path).as_deref(),
+            Some("pdf" | "txt" | "md" | "docx" | "odt" | "html" | "htm" | "xhtml" | "epub" | "rtf")
+        )
+    }
+
+    /// Get the lowercase extension of a file (so "REPORT.PDF" is processed like "report.pdf")
+    fn extension(path: &Path) -> Option<String> {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+    }
+
+    /// Extract a single source file into a document and its chunks
+    /// Parameters:
+    ///   - path: Path to the source file
+    ///   - file: Fingerprint of the file from the manifest check (size, mtime and hash)
+    ///
+    /// Returns: The extracted document and chunks
+    fn extract_file(&self, path: &Path, file: &SourceFile) -> Result<Extracted> {
+        // Check file extension to determine processing method
+        match Self::extension(path).as_deref() {
+            Some("pdf") => self.process_pdf(path, file),
+            Some("txt") | Some("md") => self.process_text_file(path, file),
+            // These extractors keep headings as "#" lines, so they get Markdown chunking
+            Some("docx") => {
+                self.process_extracted(path, file, extractors::extract_docx, TextFormat::Markdown)
+            }
+            Some("odt") => {
+                self.process_extracted(path, file, extractors::extract_odt, TextFormat::Markdown)
+            }
+            Some("html") | Some("htm") | Some("xhtml") => self.process_extracted(
+                path,
+                file,
+                extractors::extract_html_file,
+                TextFormat::Markdown,
+            ),
+            Some("epub") => {
+                self.process_extracted(path, file, extractors::extract_epub, TextFormat::Markdown)
+            }
+            Some("rtf") => {
+                self.process_extracted(path, file, extractors::extract_rtf, TextFormat::Plain)
+            }
+            _ => bail!("unsupported file type"),
+        }
+    }
+
+    /// Remove every document whose source lies at or below a (deleted) path
+    /// Parameters:
+    ///   - path: Deleted file or directory
+    ///
+    /// Returns: true if any documents were removed
+    fn remove_sources_under(&mut self, path: &Path) -> bool {
+        let sources: HashSet<String> = self
+            .documents
+            .iter()
+            .filter(|doc| Path::new(&doc.source).starts_with(path))
+            .map(|doc| doc.source.clone())
+            .collect();
+
+        for source in &sources {
+            self.remove_source(source);
+        }
+
+        !sources.is_empty()
+    }
+
+    /// Remove every document (and its chunks) that was extracted from a source file
+    /// Parameters:
+    ///   - source: Original file path of the document(s) to remove
+    fn remove_source(&mut self, source: &str) {
+        let doc_ids: HashSet<String> = self
+            .documents
+            .iter()
+            .filter(|doc| doc.source == source)
+            .map(|doc| doc.id.clone())
+            .collect();
+
+        if doc_ids.is_empty() {
+            return;
+        }
+
+        self.documents.retain(|doc| !doc_ids.contains(&doc.id));
+        self.chunks
+            .retain(|chunk| !doc_ids.contains(&chunk.document_id));
+    }
+
     /// Process a PDF file by extracting text content
     /// Parameters:
     ///   - path: Path to the PDF file
-    /// Returns: Result indicating success or failure
-    fn process_pdf(&mut self, path: &Path) -> Result<()> {
-        // Extract text content from PDF using pdf_extract crate
-        let content = extract_text(path)?;
+    ///   - file: Fingerprint of the file from the manifest check
+    ///
+    /// Returns: The extracted document and chunks
+    fn process_pdf(&self, path: &Path, file: &SourceFile) -> Result<Extracted> {
+        // Extract text page by page, so chunks can cite their pages, keeping the parsed
+        // document for its metadata (the parser panics on some malformed files,
+        // which must not abort the whole build)
+        let (pdf, pages, unreadable) = panic::catch_unwind(|| pages::load_pdf(path))
+            .map_err(|_| anyhow!("PDF parser crashed, the file is probably corrupt"))??;
+        let content = pages.join("\n\n");
+
+        // Pages that could not be read are recorded, so every build can warn about them
+        let mut pdf_metadata = metadata::pdf_metadata(&pdf);
+        if !unreadable.is_empty() {
+            let numbers: Vec<String> = unreadable.iter().map(u32::to_string).collect();
+            pdf_metadata.insert("unreadable_pages".to_string(), numbers.join(", "));
+        }
 
         // Use filename as document title
         let title = path
@@ -155,16 +589,20 @@ impl RagSystem {
             .unwrap_or("Unknown PDF")
             .to_string();
 
-        // Create document and chunks from the extracted content
-        self.create_document(title, content, path.to_string_lossy().to_string())?;
-        Ok(())
+        // Create document and chunks from the extracted content, then record their pages
+        let mut extracted =
+            self.create_document(title, content, file, TextFormat::Plain, pdf_metadata)?;
+        pages::assign_pages(&mut extracted.chunks, &pages);
+        Ok(extracted)
     }
 
     /// Process a text file (TXT or MD) by reading its content
     /// Parameters:
     ///   - path: Path to the text file
-    /// Returns: Result indicating success or failure
-    fn process_text_file(&mut self, path: &Path) -> Result<()> {
+    ///   - file: Fingerprint of the file from the manifest check
+    ///
+    /// Returns: The extracted document and chunks
+    fn process_text_file(&self, path: &Path, file: &SourceFile) -> Result<Extracted> {
         // Read the entire file content as UTF-8 string
         let content = fs::read_to_string(path)?;
 
@@ -175,23 +613,86 @@ impl RagSystem {
             .unwrap_or("Unknown Text File")
             .to_string();
 
+        // Markdown files are chunked along their heading structure, after
+        // their front matter has been moved into the document metadata
+        let (format, front_matter, content) = match Self::extension(path).as_deref() {
+            Some("md") => {
+                let (front_matter, body) = metadata::split_front_matter(&content);
+                (TextFormat::Markdown, front_matter, body.to_string())
+            }
+            _ => (TextFormat::Plain, BTreeMap::new(), content),
+        };
+
         // Create document and chunks from the file content
-        self.create_document(title, content, path.to_string_lossy().to_string())?;
-        Ok(())
+        self.create_document(title, content, file, format, front_matter)
+    }
+
+    /// Process a document whose text is obtained with one of the format extractors
+    /// Parameters:
+    ///   - path: Path to the source file
+    ///   - file: Fingerprint of the file from the manifest check
+    ///   - extract: Extractor returning the document's plain text
+    ///   - format: How the extracted text should be chunked
+    ///
+    /// Returns: The extracted document and chunks
+    fn process_extracted(
+        &self,
+        path: &Path,
+        file: &SourceFile,
+        extract: fn(&Path) -> Result<String>,
+        format: TextFormat,
+    ) -> Result<Extracted> {
+        let content = extract(path)?;
+
+        // Use filename as document title
+        let title = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Unknown Document")
+            .to_string();
+
+        // Create document and chunks from the extracted content
+        self.create_document(title, content, file, format, BTreeMap::new())
     }
 
     /// Create a document record and split it into searchable chunks
     /// Parameters:
     ///   - title: Document title (usually filename)
     ///   - content: Full text content of the document
-    ///   - source: Original file path or source identifier
-    /// Returns: Result indicating success or failure
-    fn create_document(&mut self, title: String, content: String, source: String) -> Result<()> {
-        // Generate unique ID for this document
-        let doc_id = Uuid::new_v4().to_string();
+    ///   - file: Manifest fingerprint of the source file (its path becomes the document source)
+    ///   - format: Plain text (configured chunking strategy) or Markdown (heading-aware chunking)
+    ///   - extra_metadata: Format-specific metadata (PDF info dictionary, Markdown front matter)
+    ///
+    /// Returns: The document and its chunks
+    fn create_document(
+        &self,
+        title: String,
+        content: String,
+        file: &SourceFile,
+        format: TextFormat,
+        extra_metadata: BTreeMap<String, String>,
+    ) -> Result<Extracted> {
+        // File type, size, modification time, hash and relative path of the source file
+        let source = file.path.clone();
+        let mut metadata = metadata::file_metadata(file, &self.data_dirs);
+        metadata.extend(extra_metadata);
+
+        // Derive the document ID from its source path and content hash, so rebuilding
+        // an unchanged file always gives it the same ID
+        let doc_id = stable_id(&[&source, &metadata["hash"]]);
 
         // Split document content into smaller chunks for better search granularity
-        let chunks = self.chunk_text(&content);
+        let chunks: Vec<TextChunk> = match format {
+            TextFormat::Markdown => self.chunk_markdown(&content),
+            TextFormat::Plain => self
+                .chunk_plain(&content)
+                .into_iter()
+                .map(|content| TextChunk {
+                    content,
+                    heading_path: String::new(),
+                })
+                .collect(),
+        };
 
         // Create the document record
         let document = Document {
@@ -200,67 +701,38 @@ impl RagSystem {
             content: content.clone(),
             source,
             chunk_index: 0,
-            metadata: HashMap::new(),
+            metadata,
         };
 
-        self.documents.push(document);
-
         // Create individual chunk records linked to this document
-        for (i, chunk) in chunks.iter().enumerate() {
-            let chunk_id = Uuid::new_v4().to_string();
-            let word_count = chunk.split_whitespace().count();
+        let mut doc_chunks = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            // Chunk IDs are the document ID plus the chunk's position in the document
+            let chunk_id = format!("{}-{}", doc_id, i);
+            let word_count = chunk.content.split_whitespace().count();
 
             let doc_chunk = DocumentChunk {
                 id: chunk_id,
                 document_id: doc_id.clone(),
-                content: chunk.clone(),
+                content: chunk.content,
                 chunk_index: i,
                 word_count,
+                heading_path: chunk.heading_path,
+                pages: None,
             };
-            self.chunks.push(doc_chunk);
-        }
-
-        Ok(())
-    }
-
-    /// Split text into chunks of approximately 500 words each
-    /// This improves search precision by creating smaller, more focused segments
-    /// Parameters:
-    ///   - text: The full text to be chunked
-    /// Returns: Vector of text chunks
-    fn chunk_text(&self, text: &str) -> Vec<String> {
-        // Simple chunking strategy: split by sentences and group into chunks of ~500 words
-        let sentences = self.split_into_sentences(text);
-        let mut chunks = Vec::new();
-        let mut current_chunk = String::new();
-        let mut word_count = 0;
-
-        for sentence in sentences {
-            let sentence_word_count = sentence.split_whitespace().count();
-
-            // Start a new chunk if adding this sentence would exceed 500 words
-            if word_count + sentence_word_count > 500 && !current_chunk.is_empty() {
-                chunks.push(current_chunk.trim().to_string());
-                current_chunk = String::new();
-                word_count = 0;
-            }
-
-            current_chunk.push_str(&sentence);
-            current_chunk.push(' ');
-            word_count += sentence_word_count;
+            doc_chunks.push(doc_chunk);
         }
 
-        // Add the final chunk if it has content
-        if !current_chunk.trim().is_empty() {
-            chunks.push(current_chunk.trim().to_string());
-        }
-
-        chunks
+        Ok(Extracted {
+            document,
+            chunks: doc_chunks,
+        })
     }
 
     /// Split text into individual sentences using regex pattern matching
     /// Parameters:
     ///   - text: The text to split into sentences
+    ///
     /// Returns: Vector of sentence strings
     fn split_into_sentences(&self, text: &str) -> Vec<String> {
         // Simple sentence splitting using regex - matches periods, exclamation marks, question marks
@@ -271,9 +743,29 @@ impl RagSystem {
             .collect()
     }
 
+    /// Put documents and chunks in source path order, so an incrementally updated
+    /// index is identical to one built from scratch
+    fn sort_by_source(&mut self) {
+        self.documents.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let sources: HashMap<&str, &str> = self
+            .documents
+            .iter()
+            .map(|doc| (doc.id.as_str(), doc.source.as_str()))
+            .collect();
+        self.chunks.sort_by_cached_key(|chunk| {
+            (
+                sources
+                    .get(chunk.document_id.as_str())
+                    .map(|source| source.to_string()),
+                chunk.chunk_index,
+            )
+        });
+    }
+
     /// Build a word index for TF-IDF based searching
-    /// Creates a mapping from each word to the chunk indices where it appears
-    /// This enables efficient full-text search across all document chunks
+    /// Creates a mapping from each word to the chunks where it appears, with its positions
+    /// This enables efficient full-text and phrase search across all document chunks
     fn build_word_index(&mut self) {
         for (chunk_idx, chunk) in self.chunks.iter().enumerate() {
             // Normalize and extract words from chunk content
@@ -285,169 +777,298 @@ impl RagSystem {
                 .filter(|s: &String| !s.is_empty()) // Remove empty strings
                 .collect();
 
-            // Add each word to the index with this chunk's index
-            for word in words {
-                self.word_index
-                    .entry(word)
-                    .or_insert_with(Vec::new)
-                    .push(chunk_idx);
+            // Add each word to the index with this chunk's index and the word's positions
+            let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+            for (position, word) in words.into_iter().enumerate() {
+                positions.entry(word).or_default().push(position as u32);
+            }
+            for (word, positions) in positions {
+                self.word_index.entry(word).or_default().push(Posting {
+                    chunk: chunk_idx,
+                    positions,
+                });
             }
         }
     }
 
-    /// Save the processed model to disk as JSON files
-    /// Creates three files: documents.json, chunks.json, and word_index.json
+    /// Save the processed model to disk in the binary index format
+    /// Writes a single index.bin file with a versioned header (see storage.rs)
     /// Returns: Result indicating success or failure
     fn save_model(&self) -> Result<()> {
-        let documents_path = self.agentic_dir.join("documents.json");
-        let chunks_path = self.agentic_dir.join("chunks.json");
-        let index_path = self.agentic_dir.join("word_index.json");
+        storage::write_index(
+            &self.agentic_dir.join(storage::INDEX_FILE),
+            &self.settings,
+            &self.documents,
+            &self.chunks,
+            &self.word_index,
+        )
+    }
+
+    /// Load a previously saved model from disk
+    /// Reads the binary index and populates the RAG system collections
+    /// Returns: Result<bool> - true if loaded successfully, false if no index exists,
+    ///          or an error if the index is stale or incompatible and must be rebuilt
+    pub fn load_model(&mut self) -> Result<bool> {
+        let index_path = self.agentic_dir.join(storage::INDEX_FILE);
+
+        // Check if the index file exists
+        if !index_path.exists() {
+            return Ok(false);
+        }
+
+        // Decode the index, rejecting files written by other versions or settings
+        let (_, payload) = storage::read_index(&index_path, &self.settings)?;
+
+        self.documents = payload.documents;
+        self.chunks = payload.chunks;
+        self.word_index = payload.word_index;
+
+        Ok(true)
+    }
+
+    /// Export the loaded model as human-readable JSON files
+    /// Creates three files: documents.json, chunks.json, and word_index.json
+    /// Parameters:
+    ///   - export_dir: Directory to write the JSON files into
+    ///
+    /// Returns: Result indicating success or failure
+    pub fn export_json(&self, export_dir: &Path) -> Result<()> {
+        fs::create_dir_all(export_dir)?;
 
         // Save documents as pretty-printed JSON
-        let documents_file = File::create(documents_path)?;
+        let documents_file = File::create(export_dir.join("documents.json"))?;
         let writer = BufWriter::new(documents_file);
         serde_json::to_writer_pretty(writer, &self.documents)?;
 
         // Save chunks as pretty-printed JSON
-        let chunks_file = File::create(chunks_path)?;
+        let chunks_file = File::create(export_dir.join("chunks.json"))?;
         let writer = BufWriter::new(chunks_file);
         serde_json::to_writer_pretty(writer, &self.chunks)?;
 
         // Save word index as pretty-printed JSON
-        let index_file = File::create(index_path)?;
+        let index_file = File::create(export_dir.join("word_index.json"))?;
         let writer = BufWriter::new(index_file);
-        serde_json::to_writer_pretty(writer, &self.word_index)?;
+        let word_index: BTreeMap<_, _> = self.word_index.iter().collect();
+        serde_json::to_writer_pretty(writer, &word_index)?;
 
         Ok(())
     }
 
-    /// Load a previously saved model from disk
-    /// Reads the three JSON files and populates the RAG system collections
-    /// Returns: Result<bool> - true if loaded successfully, false if files don't exist
-    pub fn load_model(&mut self) -> Result<bool> {
-        let documents_path = self.agentic_dir.join("documents.json");
-        let chunks_path = self.agentic_dir.join("chunks.json");
-        let index_path = self.agentic_dir.join("word_index.json");
-
-        // Check if all required files exist
-        // Check if all required files exist
-        if !documents_path.exists() || !chunks_path.exists() || !index_path.exists() {
-            return Ok(false);
-        }
-
-        // Load documents from JSON file
-        let documents_file = File::open(documents_path)?;
-        let reader = BufReader::new(documents_file);
-        self.documents = serde_json::from_reader(reader)?;
-
-        // Load chunks from JSON file
-        let chunks_file = File::open(chunks_path)?;
-        let reader = BufReader::new(chunks_file);
-        self.chunks = serde_json::from_reader(reader)?;
-
-        // Load word index from JSON file
-        let index_file = File::open(index_path)?;
-        let reader = BufReader::new(index_file);
-        self.word_index = serde_json::from_reader(reader)?;
-
-        Ok(true)
-    }
-
     /// Remove the local RAG model by deleting all stored files
-    /// This clears the agentic directory and recreates it empty
+    /// Only this knowledge base's index files are deleted, so the registry and
+    /// other collections stored below the agentic directory are left intact
     /// Returns: Result indicating success or failure
     pub fn remove_local_model(&self) -> Result<()> {
         colour_print("\t Removing local RAG model...", "yellow");
 
-        // Remove the entire agentic directory and recreate it empty
-        if self.agentic_dir.exists() {
-            fs::remove_dir_all(&self.agentic_dir)?;
-            fs::create_dir_all(&self.agentic_dir)?;
+        // Remove the index, manifest, build report, re-ranking cache and any JSON export
+        for file in [
+            storage::INDEX_FILE,
+            manifest::MANIFEST_FILE,
+            report::REPORT_FILE,
+            rerank::RERANK_CACHE_FILE,
+        ] {
+            let path = self.agentic_dir.join(file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        let export_dir = self.agentic_dir.join("export");
+        if export_dir.exists() {
+            fs::remove_dir_all(export_dir)?;
         }
 
         colour_print("\t Local model removed successfully", "green");
         Ok(())
     }
 
-    /// Search the local knowledge base using TF-IDF scoring
+    /// Search the local knowledge base for a query using TF-IDF scoring
+    /// Questions are usually plain words (Query::plain), --rag search queries are parsed (Query::parse)
+    /// Each chunk's relevance is reported on a 0–1 scale: the share of the query's weight
+    /// (the IDF of each word or phrase, times its bonus) carried by the terms the chunk contains.
+    /// 1.0 means the chunk contains every term; common words count for little.
+    /// The relevance is what --min-score is compared against; ranking uses the TF-IDF score.
+    /// Chunks must contain every required term and none of the excluded ones
     /// Parameters:
-    ///   - query: The search query string
+    ///   - query: The query to search for
     ///   - top_k: Maximum number of results to return
-    /// Returns: Vector of (score, chunk) tuples sorted by relevance
-    pub fn search_local(&self, query: &str, top_k: usize) -> Vec<(f32, &DocumentChunk)> {
+    ///   - min_score: Minimum relevance of a result, applied before the top_k cut
+    ///   - filter: Metadata conditions a chunk's document must meet to be scored
+    ///
+    /// Returns: Vector of (relevance, chunk) tuples ranked by TF-IDF score
+    pub fn search_query(
+        &self,
+        query: &Query,
+        top_k: usize,
+        min_score: f32,
+        filter: &MetadataFilter,
+    ) -> Vec<(f32, &DocumentChunk)> {
         if self.chunks.is_empty() {
             return Vec::new();
         }
 
-        // Normalize query words (lowercase, alphanumeric only)
-        let query_words: Vec<String> = query
-            .to_lowercase()
-            .split_whitespace()
-            .map(|s| s.chars().filter(|c| c.is_alphanumeric()).collect())
-            .filter(|s: &String| !s.is_empty())
+        // Documents by ID, for the metadata filter and field restrictions
+        let documents: HashMap<&str, &Document> = self
+            .documents
+            .iter()
+            .map(|doc| (doc.id.as_str(), doc))
             .collect();
 
-        let mut chunk_scores: HashMap<usize, f32> = HashMap::new();
-
-        // Calculate TF-IDF scores for each query word
-        for word in &query_words {
-            if let Some(chunk_indices) = self.word_index.get(word) {
-                // Document frequency: number of chunks containing this word
-                let document_frequency = chunk_indices.len() as f32;
+        // Occurrences of each word and phrase per chunk (None for field restrictions)
+        let matches: Vec<Vec<Option<HashMap<usize, usize>>>> = query
+            .clauses
+            .iter()
+            .map(|clause| {
+                clause
+                    .alternatives
+                    .iter()
+                    .map(|term| self.term_matches(term))
+                    .collect()
+            })
+            .collect();
 
-                // Inverse document frequency: log(total_chunks / document_frequency)
-                let inverse_document_frequency =
-                    ((self.chunks.len() as f32) / document_frequency).ln();
+        // IDF of each word and phrase; terms missing from the index are as rare as the rarest
+        let total_chunks = self.chunks.len() as f32;
+        let idf = |occurrences: &HashMap<usize, usize>| {
+            (total_chunks / occurrences.len().max(1) as f32).ln().abs()
+        };
 
-                for &chunk_idx in chunk_indices {
-                    if let Some(chunk) = self.chunks.get(chunk_idx) {
-                        // Term frequency: how often the word appears in this chunk
-                        let term_frequency =
-                            chunk.content.to_lowercase().matches(word).count() as f32;
-                        let normalized_tf = term_frequency / (chunk.word_count as f32);
+        // Weight of each term for the relevance scale: its IDF times its bonus
+        let mut weights: Vec<Vec<f32>> = query
+            .clauses
+            .iter()
+            .zip(&matches)
+            .map(|(clause, clause_matches)| {
+                clause
+                    .alternatives
+                    .iter()
+                    .zip(clause_matches)
+                    .map(|(term, occurrences)| match (clause.occur, occurrences) {
+                        (Occur::MustNot, _) | (_, None) => 0.0,
+                        (_, Some(occurrences)) => idf(occurrences) * term_bonus(term),
+                    })
+                    .collect()
+            })
+            .collect();
+        // A clause weighs as much as its heaviest alternative
+        let clause_weight = |weights: &[f32]| weights.iter().copied().fold(0.0, f32::max);
+        // With a single chunk every IDF is zero, so weigh the terms equally
+        if weights.iter().map(|w| clause_weight(w)).sum::<f32>() <= 0.0 {
+            for (clause, clause_weights) in query.clauses.iter().zip(&mut weights) {
+                for (term, weight) in clause.alternatives.iter().zip(clause_weights) {
+                    if clause.occur != Occur::MustNot && !matches!(term, Term::Field(..)) {
+                        *weight = 1.0;
+                    }
+                }
+            }
+        }
+        let total_weight: f32 = weights.iter().map(|w| clause_weight(w)).sum();
+
+        // Candidates: chunks containing any word or phrase that is searched for
+        let mut candidates: HashSet<usize> = HashSet::new();
+        for (clause, clause_matches) in query.clauses.iter().zip(&matches) {
+            if clause.occur != Occur::MustNot {
+                for occurrences in clause_matches.iter().flatten() {
+                    candidates.extend(occurrences.keys());
+                }
+            }
+        }
 
-                        // Use absolute value of IDF to avoid negative scores
-                        let tf_idf_score = normalized_tf * inverse_document_frequency.abs();
+        // (relevance, TF-IDF score, chunk index) of each chunk that satisfies the query
+        let mut results: Vec<(f32, f32, usize)> = Vec::new();
+        for chunk_idx in candidates {
+            let Some(chunk) = self.chunks.get(chunk_idx) else {
+                continue;
+            };
+            let Some(document) = documents.get(chunk.document_id.as_str()) else {
+                continue;
+            };
 
-                        // Add bonus for domain-specific important words
-                        let word_bonus = match word.as_str() {
-                            "toro" | "recycler" | "22" | "manual" => 2.0,
-                            _ => 1.0,
-                        };
+            // Skip chunks of documents excluded by the metadata filter
+            if !filter.is_empty() && !filter.matches(document) {
+                continue;
+            }
 
-                        *chunk_scores.entry(chunk_idx).or_insert(0.0) += tf_idf_score * word_bonus;
+            let mut score = 0.0;
+            let mut matched = 0.0;
+            let mut satisfied = true;
+            for ((clause, clause_matches), clause_weights) in
+                query.clauses.iter().zip(&matches).zip(&weights)
+            {
+                let mut clause_matched = false;
+                let mut best_weight: f32 = 0.0;
+                for ((term, occurrences), weight) in clause
+                    .alternatives
+                    .iter()
+                    .zip(clause_matches)
+                    .zip(clause_weights)
+                {
+                    let count = match occurrences {
+                        Some(occurrences) => occurrences.get(&chunk_idx).copied().unwrap_or(0),
+                        None => usize::from(term.matches_document(document)),
+                    };
+                    if count == 0 {
+                        continue;
                     }
+                    clause_matched = true;
+                    best_weight = best_weight.max(*weight);
+
+                    // Term frequency of the word or phrase, normalised by the chunk length
+                    if let Some(occurrences) = occurrences
+                        && clause.occur != Occur::MustNot
+                    {
+                        let normalized_tf = count as f32 / chunk.word_count.max(1) as f32;
+                        score += normalized_tf * idf(occurrences) * term_bonus(term);
+                    }
+                }
+
+                match clause.occur {
+                    Occur::Must if !clause_matched => satisfied = false,
+                    Occur::MustNot if clause_matched => satisfied = false,
+                    Occur::MustNot => {}
+                    _ => matched += best_weight,
                 }
+                if !satisfied {
+                    break;
+                }
+            }
+
+            // Only include chunks that satisfy the query with a positive score, or that
+            // match terms weighed equally because every IDF is zero (a single-chunk index),
+            // and that are relevant enough
+            let relevance = (matched / total_weight).min(1.0);
+            if satisfied && (score > 0.0 || matched > 0.0) && relevance >= min_score {
+                results.push((relevance, score, chunk_idx));
             }
         }
 
-        // Sort by score and return top_k results
-        let mut results: Vec<(f32, &DocumentChunk)> = chunk_scores
+        // Sort by TF-IDF score in descending order (highest first)
+        // Ties keep index order, so the same query always gives the same results
+        results.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.2.cmp(&b.2))
+        });
+        results
             .into_iter()
-            .filter_map(|(idx, score)| self.chunks.get(idx).map(|chunk| (score, chunk)))
-            .filter(|(score, _)| *score > 0.0) // Only include positive scores
-            .collect();
-
-        // Sort by score in descending order (highest first)
-        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-        results.into_iter().take(top_k).collect()
+            .take(top_k)
+            .filter_map(|(relevance, _, idx)| self.chunks.get(idx).map(|chunk| (relevance, chunk)))
+            .collect()
     }
 
     /// Get a document by its unique ID
     /// Parameters:
     ///   - doc_id: The unique identifier of the document
+    ///
     /// Returns: Option containing the document if found
     pub fn get_document_by_id(&self, doc_id: &str) -> Option<&Document> {
         self.documents.iter().find(|doc| doc.id == doc_id)
     }
 
-    /// Check if a local model is available (all required files exist)
-    /// Returns: true if all model files exist, false otherwise
+    /// Check if a local model is available (the index file exists)
+    /// Returns: true if the index file exists, false otherwise
     pub fn is_model_available(&self) -> bool {
-        let documents_path = self.agentic_dir.join("documents.json");
-        let chunks_path = self.agentic_dir.join("chunks.json");
-        let index_path = self.agentic_dir.join("word_index.json");
-        documents_path.exists() && chunks_path.exists() && index_path.exists()
+        self.agentic_dir.join(storage::INDEX_FILE).exists()
     }
 
     /// Get statistics about the loaded model
@@ -456,3 +1077,34 @@ impl RagSystem {
         (self.documents.len(), self.chunks.len())
     }
 }
+
+/// Derive a stable identifier from a list of strings
+/// Parameters:
+///   - parts: Values that together identify the item (e.g. source path and content hash)
+///
+/// Returns: The first 32 hex digits of the SHA-256 hash of the parts
+pub(crate) fn stable_id(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]); // Separator, so ("ab", "c") and ("a", "bc") differ
+    }
+    format!("{:x}", hasher.finalize())[..32].to_string()
+}
+
+/// Bonus multiplier for domain-specific important words in search scores
+fn word_bonus(word: &str) -> f32 {
+    match word {
+        "toro" | "recycler" | "22" | "manual" => 2.0,
+        _ => 1.0,
+    }
+}
+
+/// Bonus multiplier for a word or phrase: a phrase gets the bonus of its most important word
+fn term_bonus(term: &Term) -> f32 {
+    match term {
+        Term::Word(word) => word_bonus(word),
+        Term::Phrase(words) => words.iter().map(|w| word_bonus(w)).fold(1.0, f32::max),
+        Term::Field(..) => 0.0,
+    }
+}
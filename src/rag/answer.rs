@@ -0,0 +1,323 @@
+// This module holds the result of a local RAG query: the generated answers and the
+// sources they were grounded in. The same result is rendered in colour for the terminal
+// and as plain Markdown (no ANSI escape codes) for the saved output file.
+// The model is asked to cite the numbered context chunks as [1], [2], ...; the citations
+// in each answer are checked against the sources it was actually given
+
+use crate::rag::RagSystem;
+use crate::rag::context::ContextChunk;
+use crate::rag::query::highlight_terms;
+use crate::rag::reply::citation_regex;
+use crate::rag::search::snippet;
+use colored::*;
+use std::collections::BTreeSet;
+
+// Citations found in an answer, checked against the sources given to the model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Citations {
+    pub cited: BTreeSet<usize>, // Numbers of the provided sources the answer cites
+    pub invalid: BTreeSet<usize>, // Cited numbers that match no provided source
+}
+
+impl Citations {
+    /// Find the citations in an answer, e.g. "[1]", "[2][3]", "[1, 4]" or "[2-3]"
+    /// Parameters:
+    ///   - answer: The generated answer text
+    ///   - source_count: Number of sources given to the model (numbered from 1)
+    ///
+    /// Returns: The valid and invalid source numbers cited
+    pub fn find(answer: &str, source_count: usize) -> Self {
+        let mut citations = Self::default();
+
+        for group in citation_regex().captures_iter(answer) {
+            for part in group[1].split([',', ';']) {
+                // A range such as "2-4" cites every source in it
+                let bounds: Vec<usize> = part
+                    .split(['-', '–'])
+                    .filter_map(|n| n.trim().parse().ok())
+                    .collect();
+                let numbers = match bounds[..] {
+                    [first, last] if first <= last && last - first < source_count.max(1) => {
+                        first..=last
+                    }
+                    [n] | [n, _] => n..=n,
+                    _ => continue,
+                };
+                for n in numbers {
+                    if (1..=source_count).contains(&n) {
+                        citations.cited.insert(n);
+                    } else {
+                        citations.invalid.insert(n);
+                    }
+                }
+            }
+        }
+
+        citations
+    }
+
+    /// Describe problems with the citations, e.g. a cited source that was not provided
+    /// Returns: One warning per problem (empty when the citations are sound)
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !self.invalid.is_empty() {
+            let numbers: Vec<String> = self.invalid.iter().map(|n| format!("[{}]", n)).collect();
+            warnings.push(format!(
+                "The answer cites {}, which {} not among the sources provided to the model",
+                numbers.join(", "),
+                if numbers.len() == 1 { "was" } else { "were" }
+            ));
+        }
+        if self.cited.is_empty() {
+            warnings.push("The answer does not cite any of the sources".to_string());
+        }
+        warnings
+    }
+}
+
+// A document chunk that was passed to the model as context
+#[derive(Debug, Clone)]
+pub struct RagSource {
+    pub title: String,                   // Document title
+    pub source: String,                  // Source file path
+    pub pages: Option<String>,           // Page label for paged documents, e.g. "p. 4–5"
+    pub section: String,                 // Heading path for structured documents
+    pub score: f32,                      // Relevance from 0 to 1
+    pub trimmed: bool,                   // Whether the chunk was trimmed to fit the context budget
+    pub excerpt: String,                 // Words around the best match, with "…" where cut
+    pub highlights: Vec<(usize, usize)>, // Byte ranges of the query terms within the excerpt
+}
+
+impl RagSource {
+    /// Describe where the source comes from, e.g. "Lease, p. 4 > Termination"
+    pub fn citation(&self) -> String {
+        let mut citation = self.title.clone();
+        if let Some(pages) = &self.pages {
+            citation.push_str(&format!(", {}", pages));
+        }
+        if !self.section.is_empty() {
+            citation.push_str(&format!(" > {}", self.section));
+        }
+        citation
+    }
+
+    /// Render the excerpt with its query terms marked by the given function
+    fn marked_excerpt(&self, mark: impl Fn(&str) -> String) -> String {
+        let mut excerpt = String::new();
+        let mut last = 0;
+        for &(start, end) in &self.highlights {
+            excerpt.push_str(&self.excerpt[last..start]);
+            excerpt.push_str(&mark(&self.excerpt[start..end]));
+            last = end;
+        }
+        excerpt.push_str(&self.excerpt[last..]);
+        excerpt
+    }
+}
+
+// The result of answering a query from the local knowledge base
+#[derive(Debug, Clone)]
+pub struct RagAnswer {
+    pub model: String,           // Ollama model that wrote the answers
+    pub answers: Vec<String>,    // Generated answers, all from the same context
+    pub sources: Vec<RagSource>, // Chunks passed to the model, best first
+    pub context: String,         // Context text exactly as it was given to the model
+}
+
+impl RagAnswer {
+    /// Check the citations of one answer against the sources given to the model
+    /// Parameters:
+    ///   - index: Which of the answers to check
+    ///
+    /// Returns: The valid and invalid source numbers cited
+    pub fn citations(&self, index: usize) -> Citations {
+        Citations::find(&self.answers[index], self.sources.len())
+    }
+
+    /// Render one answer and its numbered references in colour for the terminal
+    /// Parameters:
+    ///   - index: Which of the answers to render
+    ///
+    /// Returns: The text to print
+    pub fn render_terminal(&self, index: usize) -> String {
+        let citations = self.citations(index);
+        let mut text = format!(
+            "\n\t📚 {}\n\t{}\n\n\t{}\n",
+            "Local Knowledge Base Response:".bold(),
+            self.answers[index].bright_cyan(),
+            "Sources used:".bold()
+        );
+        for (i, source) in self.sources.iter().enumerate() {
+            text.push_str(&format!(
+                "\t  [{}] {} (Relevance: {:.4}{}){}\n",
+                i + 1,
+                source.citation().bright_blue(),
+                source.score,
+                if source.trimmed { ", trimmed" } else { "" },
+                if citations.cited.contains(&(i + 1)) {
+                    ""
+                } else {
+                    " – not cited"
+                }
+            ));
+        }
+        for warning in citations.warnings() {
+            text.push_str(&format!("\t{}\n", format!("⚠ {}", warning).yellow()));
+        }
+        text
+    }
+
+    /// Render one answer and its numbered references as Markdown for the output file
+    /// Parameters:
+    ///   - index: Which of the answers to render
+    ///
+    /// Returns: The Markdown text, without terminal colour codes
+    pub fn render_markdown(&self, index: usize) -> String {
+        let citations = self.citations(index);
+        let mut text = format!("{}\n", self.answers[index].trim());
+        for warning in citations.warnings() {
+            text.push_str(&format!("\n> **Warning:** {}\n", warning));
+        }
+        text.push_str("\n### References\n");
+        for (i, source) in self.sources.iter().enumerate() {
+            // Escape a leading "#" so a Markdown heading in the excerpt stays plain text
+            let mut excerpt = source.marked_excerpt(|word| format!("**{}**", word));
+            if excerpt.starts_with('#') {
+                excerpt.insert(0, '\\');
+            }
+            text.push_str(&format!(
+                "\n{}. **{}** (`{}`, relevance {:.4}{}{})\n\n   > {}\n",
+                i + 1,
+                source.citation(),
+                source.source,
+                source.score,
+                if source.trimmed { ", trimmed" } else { "" },
+                if citations.cited.contains(&(i + 1)) {
+                    ""
+                } else {
+                    ", not cited"
+                },
+                excerpt
+            ));
+        }
+        text
+    }
+
+    /// Render the context given to the model as a collapsible Markdown section
+    /// The context is fenced so the document text is shown as-is rather than as Markdown
+    /// Returns: The Markdown text
+    pub fn render_context_markdown(&self) -> String {
+        // Use a fence longer than any run of backticks in the context
+        let mut fence = "```".to_string();
+        while self.context.contains(fence.as_str()) {
+            fence.push('`');
+        }
+        format!(
+            "## Context\n\n<details>\n<summary>Retrieved context given to {} ({} chunks)</summary>\n\n{}text\n{}\n{}\n\n</details>",
+            self.model,
+            self.sources.len(),
+            fence,
+            self.context.trim_end(),
+            fence
+        )
+    }
+}
+
+impl RagSystem {
+    /// Describe the chunks passed to the model as sources of an answer
+    /// Parameters:
+    ///   - query: The query the chunks were retrieved for
+    ///   - context_chunks: The chunks passed to the model, best first
+    ///
+    /// Returns: One source per chunk, with an excerpt around the query terms
+    pub fn cite_sources(&self, query: &str, context_chunks: &[ContextChunk]) -> Vec<RagSource> {
+        let terms = highlight_terms(query);
+        context_chunks
+            .iter()
+            .map(|context| {
+                let document = self.get_document_by_id(&context.chunk.document_id);
+                let (excerpt, highlights) = snippet(&context.content, &terms);
+                RagSource {
+                    title: document.map(|d| d.title.clone()).unwrap_or_default(),
+                    source: document.map(|d| d.source.clone()).unwrap_or_default(),
+                    pages: context.page_label(),
+                    section: context.chunk.heading_path.clone(),
+                    score: context.score,
+                    trimmed: context.trimmed,
+                    excerpt,
+                    highlights,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The source numbers of a citation set, in order
+    fn numbers(set: &BTreeSet<usize>) -> Vec<usize> {
+        set.iter().copied().collect()
+    }
+
+    #[test]
+    fn single_and_listed_citations_are_found() {
+        let citations =
+            Citations::find("One month [1]. Paid monthly [2][3], in advance [1, 4].", 4);
+        assert_eq!(numbers(&citations.cited), [1, 2, 3, 4]);
+        assert!(citations.invalid.is_empty());
+        assert!(citations.warnings().is_empty());
+
+        let citations = Citations::find("See [ 2 ; 3 ].", 3);
+        assert_eq!(numbers(&citations.cited), [2, 3]);
+    }
+
+    #[test]
+    fn ranges_cite_every_source_in_them() {
+        let citations = Citations::find("Covered in [2-4] and [5–6].", 6);
+        assert_eq!(numbers(&citations.cited), [2, 3, 4, 5, 6]);
+        assert!(citations.invalid.is_empty());
+    }
+
+    #[test]
+    fn huge_or_reversed_ranges_cite_only_their_start() {
+        // A range wider than the sources given is not expanded number by number
+        let citations = Citations::find("Somewhere in [1-1000000].", 3);
+        assert_eq!(numbers(&citations.cited), [1]);
+        assert!(citations.invalid.is_empty());
+
+        let citations = Citations::find("See [3-1].", 3);
+        assert_eq!(numbers(&citations.cited), [3]);
+    }
+
+    #[test]
+    fn out_of_range_citations_are_reported() {
+        let citations = Citations::find("It says so [2] and [7], also [0].", 3);
+        assert_eq!(numbers(&citations.cited), [2]);
+        assert_eq!(numbers(&citations.invalid), [0, 7]);
+        assert_eq!(
+            citations.warnings(),
+            ["The answer cites [0], [7], which were not among the sources provided to the model"]
+        );
+
+        let citations = Citations::find("Only [5].", 3);
+        assert_eq!(
+            citations.warnings(),
+            [
+                "The answer cites [5], which was not among the sources provided to the model",
+                "The answer does not cite any of the sources"
+            ]
+        );
+    }
+
+    #[test]
+    fn answers_without_citations_are_reported() {
+        let citations = Citations::find("The notice period is one month (see 2023).", 3);
+        assert!(citations.cited.is_empty() && citations.invalid.is_empty());
+        assert_eq!(
+            citations.warnings(),
+            ["The answer does not cite any of the sources"]
+        );
+    }
+}
@@ -0,0 +1,611 @@
+// This module contains the chunkers that split documents into searchable pieces
+// Plain text is split with one of several configurable strategies (sentence packing,
+// fixed windows, paragraphs or recursive splitting). Markdown is split on heading
+// boundaries, keeps fenced code blocks and tables intact, and records the heading
+// path of each chunk (e.g. "Install > Linux > Troubleshooting")
+
+use crate::rag::RagSystem;
+use crate::rag::storage::IndexSettings;
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Separator placed between headings in a chunk's heading path
+pub const HEADING_SEPARATOR: &str = " > ";
+
+// How a document's text should be split into chunks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextFormat {
+    Plain,    // Flat text, split with the configured chunking strategy
+    Markdown, // Text with "#" headings, split on heading boundaries
+}
+
+// Strategy used to split plain text into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkStrategy {
+    Sentence,  // Pack whole sentences into chunks (the original behaviour)
+    Window,    // Fixed-size sliding window over the words, ignoring sentence boundaries
+    Paragraph, // Pack whole paragraphs, splitting oversized ones by sentence
+    Recursive, // Split on paragraphs, then lines, then sentences, then words until pieces fit
+}
+
+impl ChunkStrategy {
+    /// Parse a strategy name as given on the command line
+    /// Parameters:
+    ///   - name: One of "sentence", "window", "paragraph" or "recursive"
+    ///
+    /// Returns: The strategy, or an error listing the valid names
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "sentence" => Ok(Self::Sentence),
+            "window" => Ok(Self::Window),
+            "paragraph" => Ok(Self::Paragraph),
+            "recursive" => Ok(Self::Recursive),
+            _ => bail!(
+                "unknown chunking strategy '{}' (use sentence, window, paragraph or recursive)",
+                name
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ChunkStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Sentence => "sentence",
+            Self::Window => "window",
+            Self::Paragraph => "paragraph",
+            Self::Recursive => "recursive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Unit that chunk sizes and overlaps are measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkUnit {
+    Words,  // Whitespace-separated words
+    Tokens, // Approximate model tokens (about 4 tokens for every 3 words)
+}
+
+impl ChunkUnit {
+    /// Parse a unit name as given on the command line
+    /// Parameters:
+    ///   - name: "words" or "tokens"
+    ///
+    /// Returns: The unit, or an error listing the valid names
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "words" | "word" => Ok(Self::Words),
+            "tokens" | "token" => Ok(Self::Tokens),
+            _ => bail!("unknown chunk unit '{}' (use words or tokens)", name),
+        }
+    }
+
+    /// Measure the size of a piece of text in this unit
+    pub fn measure(self, text: &str) -> usize {
+        self.scale_words(text.split_whitespace().count())
+    }
+
+    /// Convert a word count into this unit
+    pub fn scale_words(self, words: usize) -> usize {
+        match self {
+            Self::Words => words,
+            Self::Tokens => (words * 4).div_ceil(3),
+        }
+    }
+
+    /// Convert an amount in this unit into the number of words it roughly covers
+    pub fn to_words(self, amount: usize) -> usize {
+        match self {
+            Self::Words => amount,
+            Self::Tokens => amount * 3 / 4,
+        }
+    }
+}
+
+impl fmt::Display for ChunkUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Words => "words",
+            Self::Tokens => "tokens",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A chunk of text produced by the chunker, before it becomes a DocumentChunk
+#[derive(Debug, Clone)]
+pub struct TextChunk {
+    pub content: String,      // Text content of the chunk
+    pub heading_path: String, // Headings the chunk sits under, joined with " > "
+}
+
+// A Markdown section: the body blocks that follow a heading
+struct Section {
+    heading_path: Vec<String>, // Headings from the top level down to this section
+    blocks: Vec<Block>,        // Paragraphs, code blocks and tables in the section
+}
+
+// A unit of Markdown text that is never split across chunks unless it is oversized
+struct Block {
+    text: String,
+    atomic: bool, // Code blocks and tables must stay intact even when oversized
+}
+
+// Greedily packs pieces of text into chunks of up to `chunk_size`, starting each new
+// chunk with the last `chunk_overlap` worth of words from the previous one
+// (overlap is never taken from an atomic piece, so code blocks and tables stay intact)
+struct Packer<'a> {
+    settings: &'a IndexSettings,
+    current: String,      // Chunk being filled
+    size: usize,          // Size of `current` in the configured unit
+    fresh: bool, // Whether `current` holds anything besides carried-over overlap or a heading
+    overlap_start: usize, // Byte offset in `current` where text usable as overlap begins
+    heading: bool, // Whether `current` holds only a heading line
+    chunks: Vec<String>, // Finished chunks
+}
+
+impl<'a> Packer<'a> {
+    fn new(settings: &'a IndexSettings) -> Self {
+        Self {
+            settings,
+            current: String::new(),
+            size: 0,
+            fresh: false,
+            overlap_start: 0,
+            heading: false,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Add a heading line that must stay in the same chunk as the text following it
+    /// Parameters:
+    ///   - heading: The heading line
+    fn push_heading(&mut self, heading: &str) {
+        self.push(heading, "\n\n");
+        self.fresh = false;
+        self.heading = true;
+    }
+
+    /// Add a code block or table, which is kept whole and never carried over as overlap
+    /// Parameters:
+    ///   - block: Text of the block
+    fn push_atomic(&mut self, block: &str) {
+        self.push(block, "\n\n");
+        self.overlap_start = self.current.len();
+    }
+
+    /// Add a piece to the current chunk, starting a new chunk first if it would not fit
+    /// A single piece larger than a chunk is kept whole
+    /// Parameters:
+    ///   - piece: Sentence, paragraph or block of text to add
+    ///   - separator: Placed between the piece and the preceding text of the chunk
+    fn push(&mut self, piece: &str, separator: &str) {
+        let piece_size = self.settings.chunk_unit.measure(piece);
+        if self.fresh && self.size + piece_size > self.settings.chunk_size {
+            self.flush();
+        }
+
+        // A heading always stands on its own line above the text that follows it
+        if self.heading {
+            self.current.push_str("\n\n");
+        } else if !self.current.is_empty() {
+            self.current.push_str(separator);
+        }
+        self.current.push_str(piece.trim());
+        self.size += piece_size;
+        self.fresh = true;
+        self.heading = false;
+    }
+
+    /// Finish the current chunk and seed the next one with the configured overlap
+    fn flush(&mut self) {
+        if !self.fresh {
+            return;
+        }
+        self.chunks.push(self.current.trim().to_string());
+
+        let overlap_words = self
+            .settings
+            .chunk_unit
+            .to_words(self.settings.chunk_overlap);
+        let words: Vec<&str> = self.current[self.overlap_start..]
+            .split_whitespace()
+            .collect();
+        let tail = words[words.len().saturating_sub(overlap_words)..].join(" ");
+
+        self.size = self.settings.chunk_unit.measure(&tail);
+        self.current = tail;
+        self.fresh = false;
+        self.overlap_start = 0;
+    }
+
+    /// Finish packing and return every chunk produced
+    fn finish(mut self) -> Vec<String> {
+        self.flush();
+        self.chunks
+    }
+}
+
+impl RagSystem {
+    /// Split plain text into chunks using the configured chunking strategy
+    /// Sizes and overlaps are measured in `settings.chunk_unit`
+    /// Parameters:
+    ///   - text: The full text to be chunked
+    ///
+    /// Returns: Vector of text chunks
+    pub(crate) fn chunk_plain(&self, text: &str) -> Vec<String> {
+        match self.settings.chunk_strategy {
+            ChunkStrategy::Sentence => {
+                let mut packer = Packer::new(&self.settings);
+                for sentence in self.split_into_sentences(text) {
+                    packer.push(&sentence, " ");
+                }
+                packer.finish()
+            }
+            ChunkStrategy::Window => self.chunk_window(text),
+            ChunkStrategy::Paragraph => {
+                let mut packer = Packer::new(&self.settings);
+                for paragraph in split_paragraphs(text) {
+                    // Paragraphs larger than a chunk are packed sentence by sentence
+                    if self.settings.chunk_unit.measure(paragraph) > self.settings.chunk_size {
+                        for sentence in self.split_into_sentences(paragraph) {
+                            packer.push(&sentence, " ");
+                        }
+                    } else {
+                        packer.push(paragraph, "\n\n");
+                    }
+                }
+                packer.finish()
+            }
+            ChunkStrategy::Recursive => {
+                let mut packer = Packer::new(&self.settings);
+                for piece in self.split_recursive(text, 0) {
+                    packer.push(&piece, " ");
+                }
+                packer.finish()
+            }
+        }
+    }
+
+    /// Split text into fixed-size windows of words, each overlapping the previous
+    /// one by `settings.chunk_overlap`
+    /// Parameters:
+    ///   - text: The full text to be chunked
+    ///
+    /// Returns: Vector of text chunks
+    fn chunk_window(&self, text: &str) -> Vec<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let unit = self.settings.chunk_unit;
+        let window = unit.to_words(self.settings.chunk_size).max(1);
+        let overlap = unit.to_words(self.settings.chunk_overlap).min(window - 1);
+        let step = window - overlap;
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < words.len() {
+            let end = (start + window).min(words.len());
+            chunks.push(words[start..end].join(" "));
+            if end == words.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+
+    /// Break text into pieces no larger than a chunk, trying progressively finer
+    /// separators: paragraphs, then lines, then sentences, then single words
+    /// Parameters:
+    ///   - text: Text to split
+    ///   - level: Index of the separator to try next
+    ///
+    /// Returns: Pieces in document order
+    fn split_recursive(&self, text: &str, level: usize) -> Vec<String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Vec::new();
+        }
+        if self.settings.chunk_unit.measure(text) <= self.settings.chunk_size || level > 3 {
+            return vec![text.to_string()];
+        }
+
+        let parts: Vec<String> = match level {
+            0 => split_paragraphs(text).map(str::to_string).collect(),
+            1 => text.lines().map(str::to_string).collect(),
+            2 => self.split_into_sentences(text),
+            _ => return self.chunk_window(text),
+        };
+        parts
+            .iter()
+            .flat_map(|part| self.split_recursive(part, level + 1))
+            .collect()
+    }
+
+    /// Split a Markdown document into chunks along its heading structure
+    /// Each section is packed into chunks of up to `settings.chunk_size`;
+    /// oversized paragraphs are packed sentence by sentence, while code blocks
+    /// and tables are always kept whole. Overlap never crosses a section boundary
+    /// or comes out of a code block or table, and a heading is never left alone in a chunk
+    /// Parameters:
+    ///   - text: The full Markdown text
+    ///
+    /// Returns: Vector of chunks with their heading paths
+    pub(crate) fn chunk_markdown(&self, text: &str) -> Vec<TextChunk> {
+        let mut chunks = Vec::new();
+
+        for section in parse_sections(text) {
+            let heading_path = section.heading_path.join(HEADING_SEPARATOR);
+            let mut packer = Packer::new(&self.settings);
+
+            // Sections under a heading start with the heading line itself
+            let mut blocks = section.blocks.into_iter();
+            if !section.heading_path.is_empty()
+                && let Some(heading) = blocks.next()
+            {
+                packer.push_heading(&heading.text);
+            }
+
+            for block in blocks {
+                if block.atomic {
+                    packer.push_atomic(&block.text);
+                } else if self.settings.chunk_unit.measure(&block.text) > self.settings.chunk_size {
+                    // A single paragraph larger than a chunk is split by sentences
+                    for sentence in self.split_into_sentences(&block.text) {
+                        packer.push(&sentence, " ");
+                    }
+                } else {
+                    packer.push(&block.text, "\n\n");
+                }
+            }
+
+            chunks.extend(packer.finish().into_iter().map(|content| TextChunk {
+                content,
+                heading_path: heading_path.clone(),
+            }));
+        }
+
+        chunks
+    }
+}
+
+/// Split text into paragraphs separated by blank lines
+fn split_paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n")
+        .flat_map(|part| part.split("\r\n\r\n"))
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+}
+
+/// Split Markdown text into sections at ATX headings ("#" to "######")
+/// Parameters:
+///   - text: The full Markdown text
+///
+/// Returns: Sections with their heading paths and body blocks
+fn parse_sections(text: &str) -> Vec<Section> {
+    let mut sections = vec![Section {
+        heading_path: Vec::new(),
+        blocks: Vec::new(),
+    }];
+    let mut headings: Vec<(usize, String)> = Vec::new(); // (level, title) stack
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut table: Vec<&str> = Vec::new();
+    let mut fence: Option<(String, Vec<&str>)> = None; // (marker, lines) of an open code block
+
+    for line in text.lines() {
+        // Inside a fenced code block everything is kept verbatim until the closing fence
+        if let Some((marker, lines)) = fence.as_mut() {
+            lines.push(line);
+            if line.trim_start().starts_with(marker.as_str()) {
+                let block = lines.join("\n");
+                current_blocks(&mut sections).push(Block {
+                    text: block,
+                    atomic: true,
+                });
+                fence = None;
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+
+        // Table rows are collected until the first non-table line
+        if trimmed.starts_with('|') {
+            flush_paragraph(&mut sections, &mut paragraph);
+            table.push(line);
+            continue;
+        }
+        flush_table(&mut sections, &mut table);
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush_paragraph(&mut sections, &mut paragraph);
+            fence = Some((trimmed[..3].to_string(), vec![line]));
+        } else if let Some((level, title)) = parse_heading(trimmed) {
+            flush_paragraph(&mut sections, &mut paragraph);
+
+            // Pop headings at the same or deeper level, then push the new one
+            while headings.last().is_some_and(|(l, _)| *l >= level) {
+                headings.pop();
+            }
+            headings.push((level, title.clone()));
+
+            let mut section = Section {
+                heading_path: headings.iter().map(|(_, t)| t.clone()).collect(),
+                blocks: Vec::new(),
+            };
+            // Keep the heading line itself at the start of the section text
+            section.blocks.push(Block {
+                text: line.trim().to_string(),
+                atomic: false,
+            });
+            sections.push(section);
+        } else if trimmed.is_empty() {
+            flush_paragraph(&mut sections, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+
+    // Close whatever is still open at the end of the document
+    if let Some((_, lines)) = fence {
+        current_blocks(&mut sections).push(Block {
+            text: lines.join("\n"),
+            atomic: true,
+        });
+    }
+    flush_table(&mut sections, &mut table);
+    flush_paragraph(&mut sections, &mut paragraph);
+
+    // Drop sections that contain only their heading line
+    sections.retain(|section| {
+        let body_blocks = if section.heading_path.is_empty() {
+            0
+        } else {
+            1
+        };
+        section.blocks.len() > body_blocks
+    });
+    sections
+}
+
+/// Recognise an ATX heading line
+/// Parameters:
+///   - line: A line with leading whitespace removed
+///
+/// Returns: (level, title) if the line is a heading
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') && !rest.starts_with('\t') {
+        return None; // "#hashtag" is not a heading
+    }
+
+    // Strip optional closing hashes ("## Title ##")
+    let title = rest.trim().trim_end_matches('#').trim().to_string();
+    if title.is_empty() {
+        return None;
+    }
+    Some((level, title))
+}
+
+/// Get the block list of the section currently being filled
+fn current_blocks(sections: &mut [Section]) -> &mut Vec<Block> {
+    &mut sections
+        .last_mut()
+        .expect("there is always at least one section")
+        .blocks
+}
+
+/// Finish the paragraph being collected and add it to the current section
+fn flush_paragraph(sections: &mut [Section], paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    current_blocks(sections).push(Block {
+        text: paragraph.join("\n"),
+        atomic: false,
+    });
+    paragraph.clear();
+}
+
+/// Finish the table being collected and add it to the current section
+fn flush_table(sections: &mut [Section], table: &mut Vec<&str>) {
+    if table.is_empty() {
+        return;
+    }
+    current_blocks(sections).push(Block {
+        text: table.join("\n"),
+        atomic: true,
+    });
+    table.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a RAG system that chunks with the given size and overlap in words
+    fn rag_system(chunk_size: usize, chunk_overlap: usize) -> RagSystem {
+        let settings = IndexSettings {
+            chunk_size,
+            chunk_overlap,
+            ..IndexSettings::default()
+        };
+        let agentic_dir = std::env::temp_dir().join("rinfomaid-chunking-tests");
+        RagSystem::new(&agentic_dir, Vec::new(), settings)
+    }
+
+    #[test]
+    fn overlap_is_not_carried_out_of_code_blocks_or_tables() {
+        let text = "# Setup\n\n\
+                    Run the installer first.\n\n\
+                    ```sh\n\
+                    cargo build --release\n\
+                    cargo install --path .\n\
+                    ```\n\n\
+                    Then check the options.\n\n\
+                    | Option | Meaning |\n\
+                    |--------|---------|\n\
+                    | --full | Rebuild |\n\n\
+                    Finally start the watcher.";
+        let chunks = rag_system(6, 4).chunk_markdown(text);
+
+        for chunk in &chunks {
+            // Every fence that is opened is closed in the same chunk
+            assert_eq!(chunk.content.matches("```").count() % 2, 0, "{:?}", chunk);
+            // Table rows only appear as whole, unmangled lines
+            for line in chunk.content.lines().filter(|line| line.contains('|')) {
+                assert!(line.trim().starts_with('|'), "{:?}", chunk);
+                assert!(line.trim().ends_with('|'), "{:?}", chunk);
+            }
+        }
+        // The chunks after the code block and the table start without any overlap
+        assert!(
+            chunks
+                .iter()
+                .any(|chunk| chunk.content.starts_with("Then check the options."))
+        );
+        assert!(
+            chunks
+                .iter()
+                .any(|chunk| chunk.content.starts_with("Finally start the watcher."))
+        );
+    }
+
+    #[test]
+    fn headings_and_overlap_never_make_up_a_chunk_alone() {
+        let text = "# Introduction\n\n\
+                    This opening paragraph is longer than a whole chunk on its own.\n\n\
+                    ## Details\n\n\
+                    Short one.\n\n\
+                    Another short paragraph follows here.";
+        let chunks = rag_system(5, 3).chunk_markdown(text);
+
+        for chunk in &chunks {
+            assert!(
+                !chunk.content.lines().all(|line| line.starts_with('#')),
+                "heading-only chunk: {:?}",
+                chunk
+            );
+        }
+        for pair in chunks.windows(2) {
+            assert!(
+                !pair[0].content.ends_with(pair[1].content.as_str()),
+                "overlap-only chunk: {:?}",
+                pair[1]
+            );
+        }
+        assert!(
+            chunks
+                .iter()
+                .any(|chunk| chunk.content.starts_with("## Details\n\nShort one."))
+        );
+    }
+}
@@ -0,0 +1,190 @@
+// This module manages named knowledge bases (collections)
+// Each collection has its own data roots, index directory and index settings.
+// Collections are registered in agentic/collections.toml; the built-in "default"
+// collection indexes data/ into agentic/ and is only registered once its settings
+// have been changed with '--rag configure'.
+
+use crate::rag::storage::IndexSettings;
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Root directory holding the default index, the registry and named collections
+pub const AGENTIC_DIR: &str = "agentic";
+
+// Name of the built-in knowledge base used when no collection is given
+pub const DEFAULT_COLLECTION: &str = "default";
+
+// Registry file listing the named collections
+const REGISTRY_FILE: &str = "collections.toml";
+
+// Configuration of a single knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub data: Vec<PathBuf>, // Directories containing the source documents
+    pub index_dir: PathBuf, // Directory the index, manifest and exports are stored in
+    #[serde(default)]
+    pub settings: IndexSettings, // Analyzer/chunker settings for this collection
+}
+
+// All registered collections, keyed by name (sorted for stable listings)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default)]
+    pub collections: BTreeMap<String, Collection>,
+}
+
+impl Registry {
+    /// Load the collection registry from the agentic directory
+    /// Returns: The registry, or an empty one if no collections have been created yet
+    pub fn load() -> Result<Self> {
+        let path = Path::new(AGENTIC_DIR).join(REGISTRY_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Save the collection registry to the agentic directory
+    /// Returns: Result indicating success or failure
+    pub fn save(&self) -> Result<()> {
+        fs::create_dir_all(AGENTIC_DIR)?;
+        let content = toml::to_string_pretty(self)?;
+        fs::write(Path::new(AGENTIC_DIR).join(REGISTRY_FILE), content)?;
+        Ok(())
+    }
+
+    /// Look up a collection by name
+    /// Parameters:
+    ///   - name: Collection name ("default" for the built-in knowledge base)
+    ///
+    /// Returns: The collection configuration, or an error if it does not exist
+    pub fn get(&self, name: &str) -> Result<Collection> {
+        match self.collections.get(name) {
+            Some(collection) => Ok(collection.clone()),
+            None if name == DEFAULT_COLLECTION => Ok(default_collection()),
+            None => bail!(
+                "unknown knowledge base '{}'. Use 'cargo run -- --rag list' to see the available ones",
+                name
+            ),
+        }
+    }
+
+    /// Register a new named collection
+    /// Parameters:
+    ///   - name: Collection name (letters, digits, '-' and '_')
+    ///   - data: Directories containing the source documents
+    ///   - settings: Analyzer/chunker settings to build the index with
+    ///
+    /// Returns: The new collection configuration
+    pub fn create(
+        &mut self,
+        name: &str,
+        data: Vec<PathBuf>,
+        settings: IndexSettings,
+    ) -> Result<Collection> {
+        if name == DEFAULT_COLLECTION {
+            bail!("'{}' is the built-in knowledge base", DEFAULT_COLLECTION);
+        }
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!(
+                "invalid knowledge base name '{}' (use letters, digits, '-' and '_')",
+                name
+            );
+        }
+        if self.collections.contains_key(name) {
+            bail!("knowledge base '{}' already exists", name);
+        }
+        if data.is_empty() {
+            bail!("a knowledge base needs at least one data directory (--data <dir>)");
+        }
+        for dir in &data {
+            if !dir.is_dir() {
+                bail!("data directory '{}' does not exist", dir.display());
+            }
+        }
+        settings.validate()?;
+
+        let collection = Collection {
+            data,
+            index_dir: Path::new(AGENTIC_DIR).join("collections").join(name),
+            settings,
+        };
+        self.collections
+            .insert(name.to_string(), collection.clone());
+        Ok(collection)
+    }
+
+    /// Change the analyzer/chunker settings of a knowledge base
+    /// The existing index no longer matches the new settings, so the next build starts from scratch
+    /// Parameters:
+    ///   - name: Collection name ("default" for the built-in knowledge base)
+    ///   - settings: The new settings
+    ///
+    /// Returns: The updated collection configuration
+    pub fn configure(&mut self, name: &str, settings: IndexSettings) -> Result<Collection> {
+        settings.validate()?;
+
+        let mut collection = self.get(name)?;
+        collection.settings = settings;
+        self.collections
+            .insert(name.to_string(), collection.clone());
+        Ok(collection)
+    }
+
+    /// Unregister a named collection and delete its index directory
+    /// (the source documents in its data directories are not touched)
+    /// Parameters:
+    ///   - name: Collection name
+    ///
+    /// Returns: The removed collection configuration
+    pub fn delete(&mut self, name: &str) -> Result<Collection> {
+        if name == DEFAULT_COLLECTION {
+            bail!("the built-in knowledge base cannot be deleted, use '--rag remove' to clear it");
+        }
+
+        let Some(collection) = self.collections.remove(name) else {
+            bail!("unknown knowledge base '{}'", name);
+        };
+        if collection.index_dir.exists() {
+            fs::remove_dir_all(&collection.index_dir)?;
+        }
+        Ok(collection)
+    }
+
+    /// List every knowledge base, starting with the built-in default
+    /// Returns: Vector of (name, collection) pairs
+    pub fn all(&self) -> Vec<(String, Collection)> {
+        let default = self
+            .collections
+            .get(DEFAULT_COLLECTION)
+            .cloned()
+            .unwrap_or_else(default_collection);
+
+        let mut all = vec![(DEFAULT_COLLECTION.to_string(), default)];
+        all.extend(
+            self.collections
+                .iter()
+                .filter(|(name, _)| name.as_str() != DEFAULT_COLLECTION)
+                .map(|(name, collection)| (name.clone(), collection.clone())),
+        );
+        all
+    }
+}
+
+/// Configuration of the built-in knowledge base: data/ indexed into agentic/
+fn default_collection() -> Collection {
+    Collection {
+        data: vec![PathBuf::from("data")],
+        index_dir: PathBuf::from(AGENTIC_DIR),
+        settings: IndexSettings::default(),
+    }
+}
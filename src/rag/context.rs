@@ -0,0 +1,455 @@
+// This module assembles the context passed to the model for a RAG query
+// Each hit can first be expanded with its neighbouring chunks from the same document
+// ("small-to-big" retrieval), merging expansions that overlap or touch.
+// The results are added in ranking order until the context token budget is used up.
+// A chunk that does not fit whole is trimmed to the sentences that mention the query
+// terms (in their original order) rather than being cut off mid-sentence
+
+use crate::rag::chunking::ChunkUnit;
+use crate::rag::query::highlight_terms;
+use crate::rag::rerank::RerankSettings;
+use crate::rag::rewrite::RewriteSettings;
+use crate::rag::search::query_terms;
+use crate::rag::{DocumentChunk, RagSystem};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+// Smallest part of the budget worth filling with a trimmed chunk, in tokens
+const MIN_TRIMMED_TOKENS: usize = 40;
+
+// Marker placed where sentences were left out of a trimmed chunk
+const GAP_MARKER: &str = "…";
+
+// How many chunks to retrieve for a query and how much of them to pass to the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievalSettings {
+    pub top_k: usize,                     // Maximum number of chunks to retrieve
+    pub min_score: f32,                   // Minimum relevance (0–1) for a chunk to be used
+    pub max_context_tokens: usize,        // Budget for the chunk text passed to the model
+    pub expand: usize,                    // Neighbouring chunks added on each side of a hit
+    pub mmr_lambda: Option<f32>,          // Relevance/diversity trade-off for MMR (None: rank only)
+    pub max_per_document: Option<usize>,  // Most chunks any one document may contribute
+    pub rerank: Option<RerankSettings>,   // Re-ranking of the candidates by a model, if enabled
+    pub rewrite: Option<RewriteSettings>, // Extra search queries derived from the question, if enabled
+    pub verbose: bool,                    // Whether to show the search queries used
+    pub query_syntax: bool, // Whether questions are parsed with the query language (else plain words)
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self {
+            top_k: 3,
+            min_score: 0.1,
+            max_context_tokens: 2000,
+            expand: 0,
+            mmr_lambda: None,
+            max_per_document: None,
+            rerank: None,
+            rewrite: None,
+            verbose: false,
+            query_syntax: false,
+        }
+    }
+}
+
+// A retrieved chunk, possibly expanded with its neighbours, as it is passed to the model
+#[derive(Debug, Clone)]
+pub struct ContextChunk<'a> {
+    pub score: f32,                     // Relevance of the best hit, from 0 to 1
+    pub chunk: &'a DocumentChunk,       // The best-scoring hit, as stored in the index
+    pub chunks: Vec<&'a DocumentChunk>, // Every chunk whose text is included, in document order
+    pub content: String,                // Text passed to the model (all of it unless trimmed)
+    pub trimmed: bool,                  // Whether sentences were left out to fit the budget
+}
+
+impl ContextChunk<'_> {
+    /// Describe the pages the included chunks come from, e.g. "p. 42" or "p. 42–44"
+    /// Returns: The page label, or None for documents without pages
+    pub fn page_label(&self) -> Option<String> {
+        let first = self
+            .chunks
+            .iter()
+            .filter_map(|c| c.pages)
+            .map(|p| p.0)
+            .min()?;
+        let last = self
+            .chunks
+            .iter()
+            .filter_map(|c| c.pages)
+            .map(|p| p.1)
+            .max()?;
+        if first == last {
+            Some(format!("p. {}", first))
+        } else {
+            Some(format!("p. {}–{}", first, last))
+        }
+    }
+}
+
+// A run of consecutive chunks of one document, grown around one or more hits
+struct Span<'a> {
+    score: f32,             // Relevance of the best hit in the span
+    hit: &'a DocumentChunk, // The best hit in the span
+    first: usize,           // chunk_index of the first chunk
+    last: usize,            // chunk_index of the last chunk
+}
+
+impl RagSystem {
+    /// Select the text of the retrieved chunks that fits the context budget
+    /// Parameters:
+    ///   - query: The query the chunks were retrieved for
+    ///   - results: Retrieved (score, chunk) pairs, best first
+    ///   - settings: The context budget and how many neighbours to add to each hit
+    ///
+    /// Returns: (chunks to pass to the model, best first; number of retrieved sources that were
+    /// trimmed or left out to fit the budget). Hits merged by the expansion count as one source
+    pub fn build_context<'a>(
+        &'a self,
+        query: &str,
+        results: &[(f32, &'a DocumentChunk)],
+        settings: &RetrievalSettings,
+    ) -> (Vec<ContextChunk<'a>>, usize) {
+        let terms = highlight_terms(query);
+        let mut remaining = settings.max_context_tokens;
+        let mut context = Vec::new();
+
+        // Most words neighbouring chunks can repeat because of the chunk overlap setting
+        // (allowing for the overlap being rounded to whole sentences)
+        let max_overlap = 2 * self
+            .settings
+            .chunk_unit
+            .to_words(self.settings.chunk_overlap);
+
+        let spans = self.expand_hits(results, settings.expand);
+        let span_count = spans.len();
+        for (score, chunk, chunks) in spans {
+            let content = join_chunks(&chunks, max_overlap);
+            let size = ChunkUnit::Tokens.measure(&content);
+            if size <= remaining {
+                remaining -= size;
+                context.push(ContextChunk {
+                    score,
+                    chunk,
+                    chunks,
+                    content,
+                    trimmed: false,
+                });
+                continue;
+            }
+
+            // Trim the chunk to its most relevant sentences, unless too little budget is left
+            // (the best chunk is always included in some form); either way, smaller chunks
+            // further down may still fit in what is left
+            if remaining < MIN_TRIMMED_TOKENS && !context.is_empty() {
+                continue;
+            }
+            let content = trim_to_budget(&content, &terms, remaining);
+            if !content.is_empty() {
+                remaining = remaining.saturating_sub(ChunkUnit::Tokens.measure(&content));
+                context.push(ContextChunk {
+                    score,
+                    chunk,
+                    chunks,
+                    content,
+                    trimmed: true,
+                });
+            }
+        }
+
+        // Every source that did not make it into the context whole was cut by the budget
+        let whole = context.iter().filter(|chunk| !chunk.trimmed).count();
+        (context, span_count - whole)
+    }
+
+    /// Expand each hit with up to `expand` neighbouring chunks on each side
+    /// Expansions within one document that overlap or touch are merged into a single span,
+    /// which keeps the rank and score of its best hit
+    /// Parameters:
+    ///   - results: Retrieved (score, chunk) pairs, best first
+    ///   - expand: Number of neighbours to add on each side (0 keeps the hits as they are)
+    ///
+    /// Returns: (score, best hit, chunks of the span in document order), best first
+    fn expand_hits<'a>(
+        &'a self,
+        results: &[(f32, &'a DocumentChunk)],
+        expand: usize,
+    ) -> Vec<(f32, &'a DocumentChunk, Vec<&'a DocumentChunk>)> {
+        if expand == 0 {
+            return results
+                .iter()
+                .map(|&(score, chunk)| (score, chunk, vec![chunk]))
+                .collect();
+        }
+
+        // Find the chunks of the hit documents by their position
+        let documents: HashSet<&str> = results
+            .iter()
+            .map(|(_, chunk)| chunk.document_id.as_str())
+            .collect();
+        let by_position: HashMap<(&str, usize), &DocumentChunk> = self
+            .chunks
+            .iter()
+            .filter(|chunk| documents.contains(chunk.document_id.as_str()))
+            .map(|chunk| ((chunk.document_id.as_str(), chunk.chunk_index), chunk))
+            .collect();
+
+        let mut spans: Vec<Span> = Vec::new();
+        for &(score, hit) in results {
+            let mut first = hit.chunk_index.saturating_sub(expand);
+            let mut last = hit.chunk_index + expand;
+
+            // Merge with the spans of the same document this one overlaps or touches;
+            // the best-ranked of them absorbs the others
+            let mut merged_into = None;
+            let mut i = 0;
+            while i < spans.len() {
+                let span = &spans[i];
+                if span.hit.document_id == hit.document_id
+                    && first <= span.last + 1
+                    && span.first <= last + 1
+                {
+                    first = first.min(span.first);
+                    last = last.max(span.last);
+                    if merged_into.is_none() {
+                        merged_into = Some(i);
+                        i += 1;
+                    } else {
+                        spans.remove(i);
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+
+            match merged_into {
+                Some(i) => {
+                    spans[i].first = first;
+                    spans[i].last = last;
+                }
+                None => spans.push(Span {
+                    score,
+                    hit,
+                    first,
+                    last,
+                }),
+            }
+        }
+
+        spans
+            .into_iter()
+            .map(|span| {
+                let chunks = (span.first..=span.last)
+                    .filter_map(|i| {
+                        by_position
+                            .get(&(span.hit.document_id.as_str(), i))
+                            .copied()
+                    })
+                    .collect();
+                (span.score, span.hit, chunks)
+            })
+            .collect()
+    }
+}
+
+/// Join consecutive chunks of a document into one text
+/// Text repeated at the start of a chunk because of the chunk overlap setting is left out
+/// Parameters:
+///   - chunks: Consecutive chunks, in document order
+///   - max_overlap: Most words that can be repeated between neighbours (0 for no overlap)
+///
+/// Returns: The combined text
+fn join_chunks(chunks: &[&DocumentChunk], max_overlap: usize) -> String {
+    let mut text = String::new();
+    for chunk in chunks {
+        if text.is_empty() {
+            text.push_str(&chunk.content);
+            continue;
+        }
+
+        // Longest run of words ending the text so far that also starts this chunk
+        let words: Vec<&str> = chunk.content.split_whitespace().collect();
+        let previous: Vec<&str> = text.split_whitespace().collect();
+        let overlap = (1..=words.len().min(previous.len()).min(max_overlap))
+            .rev()
+            .find(|&k| previous[previous.len() - k..] == words[..k])
+            .unwrap_or(0);
+
+        if overlap == 0 {
+            text.push_str("\n\n");
+            text.push_str(&chunk.content);
+        } else if overlap < words.len() {
+            text.push(' ');
+            text.push_str(&words[overlap..].join(" "));
+        }
+    }
+    text
+}
+
+/// Keep the sentences of a chunk that best fit the budget
+/// Sentences mentioning more query terms are kept first; the kept sentences stay in their
+/// original order, with a gap marker where others were left out
+/// Parameters:
+///   - content: Chunk text
+///   - terms: Normalised query terms
+///   - max_tokens: Budget for the trimmed text, in approximate tokens
+///
+/// Returns: The trimmed text (empty if the budget is too small for a single word)
+fn trim_to_budget(content: &str, terms: &[String], max_tokens: usize) -> String {
+    let sentences = split_sentences(content);
+
+    // Rank sentences by how many query terms they mention, earlier sentences first on ties
+    let mut order: Vec<usize> = (0..sentences.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(count_terms(sentences[i], terms)));
+
+    // Text without sentence breaks (e.g. from some PDFs) is cut at a word boundary instead
+    if let Some(&best) = order.first()
+        && ChunkUnit::Tokens.measure(sentences[best]) > max_tokens
+    {
+        let words: Vec<&str> = sentences[best]
+            .split_whitespace()
+            .take(ChunkUnit::Tokens.to_words(max_tokens))
+            .collect();
+        if words.is_empty() {
+            return String::new();
+        }
+        return format!("{} {}", words.join(" "), GAP_MARKER);
+    }
+
+    let mut keep = vec![false; sentences.len()];
+    let mut remaining = max_tokens;
+    for i in order {
+        let size = ChunkUnit::Tokens.measure(sentences[i]);
+        if size <= remaining {
+            remaining -= size;
+            keep[i] = true;
+        }
+    }
+
+    // Join the kept sentences, marking the gaps left by the others
+    let mut pieces = Vec::new();
+    let mut gap = false;
+    for (sentence, kept) in sentences.iter().zip(&keep) {
+        if *kept {
+            if gap && !pieces.is_empty() {
+                pieces.push(GAP_MARKER);
+            }
+            pieces.push(sentence);
+            gap = false;
+        } else {
+            gap = true;
+        }
+    }
+    if gap && !pieces.is_empty() {
+        pieces.push(GAP_MARKER);
+    }
+    pieces.join(" ")
+}
+
+/// Split text into sentences, keeping their closing punctuation
+fn split_sentences(text: &str) -> Vec<&str> {
+    let re = Regex::new(r"[.!?]+\s+").unwrap();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for end in re.find_iter(text) {
+        sentences.push(text[start..end.end()].trim());
+        start = end.end();
+    }
+    sentences.push(text[start..].trim());
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+/// Count the words of a sentence that match a query term
+fn count_terms(sentence: &str, terms: &[String]) -> usize {
+    query_terms(sentence)
+        .iter()
+        .filter(|word| terms.contains(word))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::storage::IndexSettings;
+
+    /// Create an empty RAG system (build_context only reads its chunker settings)
+    fn rag_system() -> RagSystem {
+        let agentic_dir = std::env::temp_dir().join("rinfomaid-context-tests");
+        RagSystem::new(&agentic_dir, Vec::new(), IndexSettings::default())
+    }
+
+    /// A chunk of its own document holding the given sentences
+    fn chunk(id: &str, sentences: &[&str]) -> DocumentChunk {
+        let content = sentences.join(" ");
+        DocumentChunk {
+            id: id.to_string(),
+            document_id: format!("doc-{}", id),
+            word_count: content.split_whitespace().count(),
+            content,
+            chunk_index: 0,
+            heading_path: String::new(),
+            pages: None,
+        }
+    }
+
+    /// A sentence of the given number of words, none of them query terms
+    fn filler(words: usize) -> String {
+        let mut sentence = vec!["filler"; words].join(" ");
+        sentence.push('.');
+        sentence
+    }
+
+    /// Retrieval settings with the given context budget
+    fn budget(max_context_tokens: usize) -> RetrievalSettings {
+        RetrievalSettings {
+            max_context_tokens,
+            ..RetrievalSettings::default()
+        }
+    }
+
+    #[test]
+    fn chunks_that_fit_are_passed_whole() {
+        let (a, b) = (chunk("a", &[&filler(30)]), chunk("b", &[&filler(30)]));
+        let results = [(1.0, &a), (0.5, &b)];
+        let rag_system = rag_system();
+        let (context, cut) = rag_system.build_context("notice", &results, &budget(100));
+        assert_eq!(context.len(), 2);
+        assert!(
+            context
+                .iter()
+                .all(|c| !c.trimmed && c.content == c.chunk.content)
+        );
+        assert_eq!(cut, 0);
+    }
+
+    #[test]
+    fn smaller_chunks_still_fill_the_budget_after_one_that_does_not_fit() {
+        // 40 tokens, then 120 tokens with 20 left (too little to trim), then 8 tokens
+        let first = chunk("first", &[&filler(30)]);
+        let large = chunk("large", &[&filler(30), &filler(30), &filler(30)]);
+        let small = chunk("small", &[&filler(6)]);
+        let results = [(1.0, &first), (0.9, &large), (0.8, &small)];
+
+        let rag_system = rag_system();
+        let (context, cut) = rag_system.build_context("notice", &results, &budget(60));
+        let ids: Vec<&str> = context.iter().map(|c| c.chunk.id.as_str()).collect();
+        assert_eq!(ids, ["first", "small"]);
+        assert_eq!(cut, 1);
+    }
+
+    #[test]
+    fn the_best_chunk_is_trimmed_to_the_sentences_mentioning_the_query() {
+        let notice = "The notice period is one month.";
+        let best = chunk("best", &[&filler(40), notice, &filler(40)]);
+        let other = chunk("other", &[&filler(60)]);
+        let results = [(1.0, &best), (0.5, &other)];
+
+        let rag_system = rag_system();
+        let (context, cut) = rag_system.build_context("notice period", &results, &budget(30));
+        assert_eq!(context.len(), 1);
+        assert!(context[0].trimmed);
+        assert_eq!(context[0].content, format!("{} {}", notice, GAP_MARKER));
+        assert!(ChunkUnit::Tokens.measure(&context[0].content) <= 30);
+        assert_eq!(cut, 2);
+    }
+}
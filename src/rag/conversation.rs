@@ -0,0 +1,122 @@
+// This module keeps the history of a conversational RAG session
+// A follow-up such as "what about its weight?" shares no words with the passages that
+// answer it, so before retrieval each follow-up is rewritten by an Ollama model into a
+// standalone question, using the recent turns of the conversation. The answer is then
+// written from the fresh context together with the history.
+// The transcript records each turn with the sources its answer was grounded in
+
+use crate::rag::answer::RagAnswer;
+use crate::rag::reply::{strip_citations, strip_thinking};
+use anyhow::{Result, anyhow};
+use ollama_rs::Ollama;
+use ollama_rs::generation::completion::request::GenerationRequest;
+
+// Number of recent turns passed to the model when condensing and answering
+const MAX_HISTORY_TURNS: usize = 6;
+
+// One question of the conversation and its answer
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub question: String,          // The question as the user asked it
+    pub query: String,             // The standalone question used for retrieval and answering
+    pub answer: Option<RagAnswer>, // The answer and its sources (None: nothing relevant was found)
+}
+
+impl Turn {
+    /// The answer text as it is passed back to the model in the history
+    fn answer_text(&self) -> String {
+        match &self.answer {
+            Some(answer) => answer.answers.first().cloned().unwrap_or_default(),
+            None => "Not found in the documents.".to_string(),
+        }
+    }
+}
+
+// The turns of a conversation, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    pub turns: Vec<Turn>,
+}
+
+impl Conversation {
+    /// Render the recent turns for a prompt, e.g. "User: ...\nAssistant: ..."
+    /// Citation markers are left out of the answers, since they refer to earlier contexts
+    /// Returns: The history text (empty before the first turn)
+    pub fn history(&self) -> String {
+        let recent = &self.turns[self.turns.len().saturating_sub(MAX_HISTORY_TURNS)..];
+        recent
+            .iter()
+            .map(|turn| {
+                format!(
+                    "User: {}\nAssistant: {}\n",
+                    turn.query,
+                    strip_citations(turn.answer_text().trim())
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Rewrite a follow-up question into a standalone question using the history
+    /// Parameters:
+    ///   - question: The question as the user asked it
+    ///   - model: Ollama model that rewrites the question
+    ///
+    /// Returns: The standalone question (the question itself before the first turn) or an error
+    pub async fn condense(&self, question: &str, model: &str) -> Result<String> {
+        if self.turns.is_empty() {
+            return Ok(question.to_string());
+        }
+
+        let prompt = format!(
+            "Given the conversation below and a follow-up question, rewrite the follow-up question \
+             as a standalone question that can be understood without the conversation. \
+             Replace pronouns and references such as \"it\" or \"that one\" with what they refer to. \
+             Reply with the standalone question only.\n\nConversation:\n{}\nFollow-up question: {}\n\nStandalone question:",
+            self.history(),
+            question
+        );
+        let ollama = Ollama::new("http://localhost".to_string(), 11434);
+        let response = ollama
+            .generate(GenerationRequest::new(model.to_string(), prompt))
+            .await
+            .map_err(|e| anyhow!("model '{}' failed: {}", model, e))?;
+
+        // Keep the first line of the reply, without the reasoning of "thinking" models
+        let reply = strip_thinking(&response.response);
+        let standalone = reply
+            .lines()
+            .map(|line| line.trim().trim_matches('"').trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or(question);
+        Ok(standalone.to_string())
+    }
+
+    /// Render the conversation as a Markdown transcript, with the sources of every answer
+    /// Parameters:
+    ///   - model: Ollama model that wrote the answers
+    ///   - grounding: Which knowledge base the answers are grounded in
+    ///
+    /// Returns: The Markdown text, without terminal colour codes
+    pub fn render_markdown(&self, model: &str, grounding: &str) -> String {
+        let mut text = format!(
+            "# Local RAG Conversation\n\n## Model: {} (local RAG)\n\n## Grounding\n\n{}\n",
+            model, grounding
+        );
+        for (i, turn) in self.turns.iter().enumerate() {
+            text.push_str(&format!(
+                "\n## Turn {}\n\n**Question:** {}\n",
+                i + 1,
+                turn.question
+            ));
+            if turn.query != turn.question {
+                text.push_str(&format!("\n**Standalone question:** {}\n", turn.query));
+            }
+            match &turn.answer {
+                Some(answer) => text.push_str(&format!("\n{}", answer.render_markdown(0))),
+                None => text.push_str("\nNot found in the documents.\n"),
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,111 @@
+// This module picks the final chunks for a RAG query from a larger pool of candidates,
+// so the context is not spent on near-identical chunks of a single document.
+// Maximal marginal relevance (MMR) trades each candidate's rank against its
+// similarity to the chunks already picked; a per-document cap limits how many chunks
+// any one document may contribute. Similarity is the Jaccard overlap of the chunks' words
+
+use crate::rag::DocumentChunk;
+use crate::rag::context::RetrievalSettings;
+use crate::rag::search::query_terms;
+use std::collections::{HashMap, HashSet};
+
+// Number of candidates retrieved for every chunk finally kept, when diversity is enabled
+const CANDIDATES_PER_RESULT: usize = 4;
+
+// Smallest candidate pool worth diversifying
+const MIN_CANDIDATES: usize = 20;
+
+impl RetrievalSettings {
+    /// Whether the final chunks are picked for diversity rather than by rank alone
+    pub fn diversifies(&self) -> bool {
+        self.mmr_lambda.is_some() || self.max_per_document.is_some()
+    }
+
+    /// Number of candidates to retrieve before re-ranking and picking the final top_k chunks
+    pub fn candidate_count(&self) -> usize {
+        let pool = if self.diversifies() {
+            (self.top_k * CANDIDATES_PER_RESULT).max(MIN_CANDIDATES)
+        } else {
+            self.top_k
+        };
+        match &self.rerank {
+            Some(rerank) => pool.max(rerank.candidates),
+            None => pool,
+        }
+    }
+}
+
+/// Pick up to top_k chunks from the candidates, balancing rank and diversity
+/// The candidates' order (by TF-IDF score, or by the re-ranking model) decides how good
+/// each one is; their relevance scores are only carried along
+/// Parameters:
+///   - candidates: Retrieved (relevance, chunk) pairs, best first
+///   - settings: top_k, the MMR lambda and the per-document cap
+///
+/// Returns: The picked (relevance, chunk) pairs in the order they were picked
+pub fn select<'a>(
+    candidates: Vec<(f32, &'a DocumentChunk)>,
+    settings: &RetrievalSettings,
+) -> Vec<(f32, &'a DocumentChunk)> {
+    if !settings.diversifies() {
+        return candidates.into_iter().take(settings.top_k).collect();
+    }
+
+    // lambda = 1 keeps the candidates' order; lower values favour chunks unlike those picked
+    let lambda = settings.mmr_lambda.unwrap_or(1.0);
+    let words: Vec<HashSet<String>> = candidates
+        .iter()
+        .map(|(_, chunk)| query_terms(&chunk.content).into_iter().collect())
+        .collect();
+
+    let mut picked: Vec<usize> = Vec::new();
+    let mut per_document: HashMap<&str, usize> = HashMap::new();
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+
+    while picked.len() < settings.top_k {
+        // Leave out documents that have reached the cap
+        remaining.retain(|&i| {
+            settings.max_per_document.is_none_or(|cap| {
+                per_document
+                    .get(candidates[i].1.document_id.as_str())
+                    .is_none_or(|&count| count < cap)
+            })
+        });
+
+        // Marginal relevance: the candidate's rank, from 1 for the first down towards 0 for
+        // the last, less the similarity to the closest chunk picked so far
+        // (the earliest candidate wins ties, so lambda = 1 keeps the original ranking)
+        let mut best: Option<(usize, f32)> = None;
+        for (position, &i) in remaining.iter().enumerate() {
+            let rank_score = 1.0 - i as f32 / candidates.len() as f32;
+            let redundancy = picked
+                .iter()
+                .map(|&j| similarity(&words[i], &words[j]))
+                .fold(0.0, f32::max);
+            let score = lambda * rank_score - (1.0 - lambda) * redundancy;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((position, score));
+            }
+        }
+
+        let Some((position, _)) = best else {
+            break;
+        };
+        let i = remaining.remove(position);
+        *per_document
+            .entry(candidates[i].1.document_id.as_str())
+            .or_insert(0) += 1;
+        picked.push(i);
+    }
+
+    picked.into_iter().map(|i| candidates[i]).collect()
+}
+
+/// Jaccard similarity of two word sets (shared words over all words), from 0 to 1
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
@@ -0,0 +1,599 @@
+// This module extracts plain text from document formats beyond PDF/TXT/MD:
+// DOCX and ODT (zipped XML), HTML, EPUB (zipped XHTML) and RTF.
+// Headings are kept as Markdown-style "#" lines so the document structure survives
+// extraction, and page furniture (scripts, navigation, headers/footers) is dropped.
+
+use anyhow::{Result, anyhow};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use scraper::{ElementRef, Html, Node};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+// HTML elements whose content is never part of the readable text
+const HTML_SKIPPED: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form",
+    "svg", "iframe", "button", "select",
+];
+
+// HTML elements that start a new line of text
+const HTML_BLOCKS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "table",
+    "tr",
+    "ul",
+    "ol",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "figure",
+    "figcaption",
+    "hr",
+    "br",
+];
+
+// RTF destinations (groups) that hold formatting data rather than document text
+const RTF_SKIPPED: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "headerl",
+    "headerr",
+    "footerl",
+    "footerr",
+    "themedata",
+    "colorschememapping",
+    "datastore",
+    "latentstyles",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+];
+
+/// Extract the text of a Word document (DOCX), keeping headings
+/// Parameters:
+///   - path: Path to the .docx file
+///
+/// Returns: Paragraphs separated by blank lines, headings prefixed with "#"
+pub fn extract_docx(path: &Path) -> Result<String> {
+    let xml = read_zip_entry(path, "word/document.xml")?;
+    let mut reader = Reader::from_str(&xml);
+
+    let mut text = String::new();
+    let mut paragraph = String::new();
+    let mut heading_level = 0;
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"w:pStyle" => {
+                // Paragraph styles "Heading1".."Heading9" and "Title" mark headings
+                if let Some(style) = attribute(&e, b"w:val") {
+                    heading_level = docx_heading_level(&style);
+                }
+            }
+            Event::Start(e) => match e.name().as_ref() {
+                b"w:p" => {
+                    paragraph.clear();
+                    heading_level = 0;
+                }
+                b"w:t" => in_text = true,
+                _ => {}
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"w:tab" => paragraph.push('\t'),
+                b"w:br" | b"w:cr" => paragraph.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => paragraph.push_str(&e.unescape()?),
+            Event::End(e) => match e.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => push_block(&mut text, &paragraph, heading_level),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(text.trim_end().to_string())
+}
+
+/// Map a DOCX paragraph style name to a heading level (0 for body text)
+fn docx_heading_level(style: &str) -> usize {
+    if style == "Title" {
+        return 1;
+    }
+    style
+        .strip_prefix("Heading")
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Extract the text of an OpenDocument text file (ODT), keeping headings
+/// Parameters:
+///   - path: Path to the .odt file
+///
+/// Returns: Paragraphs separated by blank lines, headings prefixed with "#"
+pub fn extract_odt(path: &Path) -> Result<String> {
+    let xml = read_zip_entry(path, "content.xml")?;
+    let mut reader = Reader::from_str(&xml);
+
+    let mut text = String::new();
+    let mut paragraph = String::new();
+    let mut heading_level = 0;
+    let mut depth = 0; // Nesting depth of text:p / text:h elements
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => {
+                    // A nested paragraph (e.g. in a note) ends the text before it
+                    if depth > 0 {
+                        push_block(&mut text, &paragraph, heading_level);
+                        paragraph.clear();
+                    }
+                    depth += 1;
+                    heading_level = if e.name().as_ref() == b"text:h" {
+                        attribute(&e, b"text:outline-level")
+                            .and_then(|level| level.parse().ok())
+                            .unwrap_or(1)
+                    } else {
+                        0
+                    };
+                }
+                _ => {}
+            },
+            Event::Empty(e) => match e.name().as_ref() {
+                b"text:s" => {
+                    let count = attribute(&e, b"text:c")
+                        .and_then(|c| c.parse().ok())
+                        .unwrap_or(1);
+                    paragraph.push_str(&" ".repeat(count));
+                }
+                b"text:tab" => paragraph.push('\t'),
+                b"text:line-break" => paragraph.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if depth > 0 => paragraph.push_str(&e.unescape()?),
+            Event::End(e) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => {
+                    push_block(&mut text, &paragraph, heading_level);
+                    paragraph.clear();
+                    heading_level = 0;
+                    depth -= 1;
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(text.trim_end().to_string())
+}
+
+/// Extract the readable text of an HTML file
+/// Parameters:
+///   - path: Path to the .html/.htm/.xhtml file
+///
+/// Returns: Clean text with scripts, styles and navigation removed
+pub fn extract_html_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(html_to_text(&String::from_utf8_lossy(&bytes)))
+}
+
+/// Convert an HTML document to clean text
+/// Headings become "#" lines, list items become "- " lines, and elements that are
+/// not part of the main text (scripts, styles, navigation, headers, footers) are skipped
+/// Parameters:
+///   - html: The HTML source
+///
+/// Returns: Extracted text with one block per line
+pub fn html_to_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut text = String::new();
+    html_element_text(document.root_element(), &mut text, false);
+    normalise_lines(&text)
+}
+
+/// Recursively append the text of an HTML element and its children
+/// Line breaks in the source only separate words, except inside <pre>
+fn html_element_text(element: ElementRef, text: &mut String, preformatted: bool) {
+    let name = element.value().name();
+    if HTML_SKIPPED.contains(&name)
+        || element.value().attr("role") == Some("navigation")
+        || element.value().attr("aria-hidden") == Some("true")
+        || element.value().attr("hidden").is_some()
+    {
+        return;
+    }
+
+    // Headings and list items get Markdown-style markers on their own line
+    let heading_level = match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => name[1..].parse().unwrap_or(1),
+        _ => 0,
+    };
+    if heading_level > 0 {
+        text.push_str(&format!("\n\n{} ", "#".repeat(heading_level)));
+    } else if name == "li" {
+        text.push_str("\n- ");
+    } else if HTML_BLOCKS.contains(&name) {
+        text.push('\n');
+    } else if name == "td" || name == "th" {
+        text.push(' ');
+    }
+
+    let preformatted = preformatted || name == "pre";
+    for child in element.children() {
+        match child.value() {
+            Node::Text(t) if preformatted => text.push_str(t),
+            Node::Text(t) => text.push_str(&t.replace(['\r', '\n'], " ")),
+            Node::Element(_) => {
+                if let Some(child_element) = ElementRef::wrap(child) {
+                    html_element_text(child_element, text, preformatted);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if heading_level > 0 || HTML_BLOCKS.contains(&name) {
+        text.push('\n');
+    }
+}
+
+/// Extract the text of an EPUB e-book, chapter by chapter in reading order
+/// Parameters:
+///   - path: Path to the .epub file
+///
+/// Returns: Text of all chapters in spine order
+pub fn extract_epub(path: &Path) -> Result<String> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+    // The container file points at the package (OPF) document
+    let container = read_archive_entry(&mut archive, "META-INF/container.xml")?;
+    let opf_path = find_attribute(&container, b"rootfile", b"full-path")?
+        .ok_or_else(|| anyhow!("EPUB container does not name a package document"))?;
+    let opf = read_archive_entry(&mut archive, &opf_path)?;
+    let opf_dir = Path::new(&opf_path)
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Collect the manifest (id -> href) and the spine (reading order of ids)
+    let mut manifest = Vec::new();
+    let mut spine = Vec::new();
+    let mut reader = Reader::from_str(&opf);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attribute(&e, b"id"), attribute(&e, b"href")) {
+                        manifest.push((id, href));
+                    }
+                }
+                b"itemref" => {
+                    if let Some(idref) = attribute(&e, b"idref") {
+                        spine.push(idref);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut text = String::new();
+    for idref in spine {
+        let Some((_, href)) = manifest.iter().find(|(id, _)| *id == idref) else {
+            continue;
+        };
+        let entry = if opf_dir.is_empty() {
+            href.clone()
+        } else {
+            format!("{}/{}", opf_dir, href)
+        };
+        let chapter = read_archive_entry(&mut archive, &entry)?;
+        text.push_str(&html_to_text(&chapter));
+        text.push_str("\n\n");
+    }
+
+    Ok(text.trim().to_string())
+}
+
+/// Extract the text of a Rich Text Format (RTF) document
+/// Parameters:
+///   - path: Path to the .rtf file
+///
+/// Returns: Plain text with paragraphs on separate lines
+pub fn extract_rtf(path: &Path) -> Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(rtf_to_text(&String::from_utf8_lossy(&bytes)))
+}
+
+/// Convert RTF source to plain text
+/// Control words are interpreted for paragraphs, tabs and special characters;
+/// formatting groups such as font tables, pictures and headers are skipped
+/// Parameters:
+///   - rtf: The RTF source
+///
+/// Returns: Plain text
+pub fn rtf_to_text(rtf: &str) -> String {
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut text = String::new();
+    let mut skip_stack: Vec<bool> = Vec::new();
+    let mut skipping = false;
+    let mut group_start = false; // True right after '{', where destinations are named
+    let mut fallback_chars = 0; // Characters to drop after a \uN unicode escape
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '{' => {
+                skip_stack.push(skipping);
+                group_start = true;
+                i += 1;
+                continue;
+            }
+            '}' => {
+                skipping = skip_stack.pop().unwrap_or(false);
+                i += 1;
+            }
+            '\\' if i + 1 < chars.len() => {
+                let next = chars[i + 1];
+                if next.is_ascii_alphabetic() {
+                    // Control word: letters, optional signed number, optional space delimiter
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < chars.len() && chars[end].is_ascii_alphabetic() {
+                        end += 1;
+                    }
+                    let word: String = chars[start..end].iter().collect();
+                    let number_start = end;
+                    if end < chars.len() && chars[end] == '-' {
+                        end += 1;
+                    }
+                    while end < chars.len() && chars[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                    let number: Option<i32> = chars[number_start..end]
+                        .iter()
+                        .collect::<String>()
+                        .parse()
+                        .ok();
+                    if end < chars.len() && chars[end] == ' ' {
+                        end += 1;
+                    }
+                    i = end;
+
+                    if group_start && RTF_SKIPPED.contains(&word.as_str()) {
+                        skipping = true;
+                    }
+                    if skipping {
+                        group_start = false;
+                        continue;
+                    }
+                    match word.as_str() {
+                        "par" | "line" | "sect" | "page" | "row" => text.push('\n'),
+                        "tab" | "cell" => text.push('\t'),
+                        "emdash" => text.push('—'),
+                        "endash" => text.push('–'),
+                        "lquote" => text.push('‘'),
+                        "rquote" => text.push('’'),
+                        "ldblquote" => text.push('“'),
+                        "rdblquote" => text.push('”'),
+                        "bullet" => text.push('•'),
+                        "u" => {
+                            if let Some(code) = number {
+                                let code = if code < 0 { code + 65536 } else { code };
+                                if let Some(ch) = char::from_u32(code as u32) {
+                                    text.push(ch);
+                                }
+                                fallback_chars = 1;
+                            }
+                        }
+                        _ => {}
+                    }
+                } else {
+                    i += 2;
+                    match next {
+                        // Ignorable destination: skip the whole group
+                        '*' => skipping = true,
+                        // Hex-escaped byte in the document code page (treated as Latin-1)
+                        '\'' => {
+                            let hex: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+                            i += hex.len();
+                            if fallback_chars > 0 {
+                                fallback_chars -= 1;
+                            } else if !skipping && let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                                text.push(byte as char);
+                            }
+                        }
+                        '\\' | '{' | '}' if !skipping => text.push(next),
+                        '~' if !skipping => text.push(' '),
+                        '_' if !skipping => text.push('-'),
+                        '\n' | '\r' if !skipping => text.push('\n'),
+                        _ => {}
+                    }
+                }
+            }
+            '\r' | '\n' => i += 1,
+            _ => {
+                if fallback_chars > 0 {
+                    fallback_chars -= 1;
+                } else if !skipping {
+                    text.push(c);
+                }
+                i += 1;
+            }
+        }
+        group_start = false;
+    }
+
+    normalise_lines(&text)
+}
+
+/// Read a UTF-8 entry from a zip archive file
+/// Parameters:
+///   - path: Path to the zip-based document
+///   - entry: Name of the entry inside the archive
+///
+/// Returns: The entry contents as a string
+fn read_zip_entry(path: &Path, entry: &str) -> Result<String> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    read_archive_entry(&mut archive, entry)
+}
+
+/// Read a UTF-8 entry from an open zip archive
+fn read_archive_entry<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    entry: &str,
+) -> Result<String> {
+    let mut file = archive
+        .by_name(entry)
+        .map_err(|e| anyhow!("missing '{}' in archive: {}", entry, e))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Get the unescaped value of an XML attribute
+fn attribute(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.to_string())
+}
+
+/// Find the first element with the given local name and return one of its attributes
+fn find_attribute(xml: &str, element: &[u8], name: &[u8]) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == element => {
+                return Ok(attribute(&e, name));
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Append a paragraph to the extracted text, as a "#" heading if it has a level
+fn push_block(text: &mut String, paragraph: &str, heading_level: usize) {
+    let paragraph = paragraph.trim();
+    if paragraph.is_empty() {
+        return;
+    }
+    if heading_level > 0 {
+        text.push_str(&"#".repeat(heading_level.min(6)));
+        text.push(' ');
+    }
+    text.push_str(paragraph);
+    text.push_str("\n\n");
+}
+
+/// Collapse runs of spaces within lines and runs of blank lines between them
+fn normalise_lines(text: &str) -> String {
+    let mut result = String::new();
+    let mut blank_lines = 0;
+
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !result.is_empty() {
+            result.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        result.push_str(&line);
+        blank_lines = 0;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtf_paragraphs_and_special_characters_become_text() {
+        let rtf = r"{\rtf1\ansi{\fonttbl{\f0 Helvetica;}}{\colortbl;\red0\green0\blue0;}
+\f0\fs24 Notice period\par
+One month\emdash paid in advance.\par
+{\*\generator Word;}Caf\'e9 \u8364? 20\tab net\par}";
+        assert_eq!(
+            rtf_to_text(rtf),
+            "Notice period\nOne month—paid in advance.\nCafé € 20 net"
+        );
+    }
+
+    #[test]
+    fn rtf_escaped_braces_and_backslashes_are_kept() {
+        assert_eq!(
+            rtf_to_text(r"{\rtf1 Use \{braces\} and \\ here.}"),
+            r"Use {braces} and \ here."
+        );
+    }
+
+    #[test]
+    fn html_keeps_headings_and_lists_and_skips_page_furniture() {
+        let html = "<html><head><title>Manual</title><style>p { color: red; }</style></head>
+            <body>
+              <nav><a href='/'>Home</a></nav>
+              <header>Site banner</header>
+              <h1>Astrobill</h1>
+              <p>The robot   weighs
+                 <b>12 kg</b>.</p>
+              <h2>Safety</h2>
+              <ul><li>Keep dry</li><li>Charge daily</li></ul>
+              <div hidden>Secret</div>
+              <script>alert('x')</script>
+              <footer>Copyright</footer>
+            </body></html>";
+        assert_eq!(
+            html_to_text(html),
+            "# Astrobill\n\nThe robot weighs 12 kg.\n\n## Safety\n\n- Keep dry\n- Charge daily"
+        );
+    }
+
+    #[test]
+    fn html_table_cells_are_separated() {
+        let html = "<table><tr><th>Part</th><th>Weight</th></tr>\
+                    <tr><td>Arm</td><td>2 kg</td></tr></table>";
+        let text = html_to_text(html);
+        let rows: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(rows, ["Part Weight", "Arm 2 kg"]);
+    }
+
+    #[test]
+    fn html_preformatted_text_keeps_its_lines() {
+        let html = "<p>Run\n  the installer:</p><pre>cargo build\ncargo install</pre>";
+        assert_eq!(
+            html_to_text(html),
+            "Run the installer:\n\ncargo build\ncargo install"
+        );
+    }
+}
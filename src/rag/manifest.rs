@@ -0,0 +1,151 @@
+// This module tracks the source files that make up the local knowledge base
+// The manifest records each file's path, size, modification time and content hash
+// so that a rebuild only has to re-extract the files that actually changed
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// File name of the manifest inside the index directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+// Fingerprint of a single source file as it was when last indexed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SourceFile {
+    pub path: String,  // File path (matches Document::source)
+    pub size: u64,     // File size in bytes
+    pub modified: u64, // Modification time in seconds since the Unix epoch
+    pub hash: String,  // SHA-256 hash of the file contents (hex encoded)
+}
+
+// Collection of all source files known to the index, keyed by path
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<String, SourceFile>,
+}
+
+// Outcome of comparing a file on disk against the manifest
+pub enum FileStatus {
+    Unchanged,           // Size, mtime or hash match the manifest entry
+    New(SourceFile),     // File is not in the manifest yet
+    Changed(SourceFile), // File is in the manifest but its contents differ
+}
+
+impl Manifest {
+    /// Load the manifest from disk
+    /// Parameters:
+    ///   - path: Location of the manifest JSON file
+    ///
+    /// Returns: The stored manifest, or an empty one if the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the manifest to disk as pretty-printed JSON
+    /// Parameters:
+    ///   - path: Location of the manifest JSON file
+    ///
+    /// Returns: Result indicating success or failure
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Compare a file on disk with its manifest entry
+    /// The content hash is only computed when the size or mtime has changed,
+    /// so touching a file without editing it does not trigger re-extraction
+    /// Parameters:
+    ///   - path: Path to the source file
+    ///
+    /// Returns: Whether the file is unchanged, new or changed
+    pub fn check(&self, path: &Path) -> Result<FileStatus> {
+        let key = path.to_string_lossy().to_string();
+        let (size, modified) = file_stats(path)?;
+
+        match self.files.get(&key) {
+            Some(entry) if entry.size == size && entry.modified == modified => {
+                Ok(FileStatus::Unchanged)
+            }
+            Some(entry) => {
+                let hash = hash_file(path)?;
+                if entry.hash == hash {
+                    Ok(FileStatus::Unchanged)
+                } else {
+                    Ok(FileStatus::Changed(SourceFile {
+                        path: key,
+                        size,
+                        modified,
+                        hash,
+                    }))
+                }
+            }
+            None => Ok(FileStatus::New(SourceFile {
+                path: key,
+                size,
+                modified,
+                hash: hash_file(path)?,
+            })),
+        }
+    }
+
+    /// Record (or replace) the fingerprint of a source file
+    pub fn insert(&mut self, file: SourceFile) {
+        self.files.insert(file.path.clone(), file);
+    }
+
+    /// Refresh the size and mtime of an unchanged file so later checks stay cheap
+    /// Parameters:
+    ///   - path: Path to the source file
+    pub fn touch(&mut self, path: &Path) -> Result<()> {
+        let key = path.to_string_lossy().to_string();
+        let (size, modified) = file_stats(path)?;
+        if let Some(entry) = self.files.get_mut(&key) {
+            entry.size = size;
+            entry.modified = modified;
+        }
+        Ok(())
+    }
+}
+
+/// Read the size and modification time (seconds since the epoch) of a file
+fn file_stats(path: &Path) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+/// Compute the SHA-256 hash of a file's contents as a hex string
+/// Parameters:
+///   - path: Path to the file to hash
+///
+/// Returns: Lowercase hex encoded digest
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
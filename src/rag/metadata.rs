@@ -0,0 +1,390 @@
+// This module collects document metadata at ingestion time and filters documents on it
+// Every document records its file type, size, modification time, content hash and
+// path relative to its data directory. PDF files add the author, title and creation
+// date from their info dictionary; Markdown files add the keys of their front matter.
+
+use crate::rag::Document;
+use crate::rag::manifest::SourceFile;
+use anyhow::{Result, anyhow, bail};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Metadata keys derived from the file itself; front matter cannot override them
+const FILE_KEYS: [&str; 5] = ["type", "size", "modified", "hash", "path"];
+
+/// Collect the metadata every source file has
+/// The size, modification time and hash come from the manifest check, so the file
+/// is not read again
+/// Parameters:
+///   - file: Fingerprint of the source file from the manifest
+///   - data_dirs: Data directories of the knowledge base (for the relative path)
+///
+/// Returns: Map with the type, size, modified, hash and path keys
+pub fn file_metadata(file: &SourceFile, data_dirs: &[PathBuf]) -> BTreeMap<String, String> {
+    let path = Path::new(&file.path);
+
+    // Path relative to the data directory containing the file, with '/' separators
+    let relative = data_dirs
+        .iter()
+        .find_map(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    let relative: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert(
+        "type".to_string(),
+        path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+    );
+    metadata.insert("size".to_string(), file.size.to_string());
+    metadata.insert("modified".to_string(), format_timestamp(file.modified));
+    metadata.insert("hash".to_string(), file.hash.clone());
+    metadata.insert("path".to_string(), relative.join("/"));
+    metadata
+}
+
+/// Read the author, title and creation date from a PDF's info dictionary
+/// PDFs without an info dictionary (or with unreadable entries) yield fewer keys
+/// Parameters:
+///   - doc: The parsed PDF document
+///
+/// Returns: Map with any of the author, title and created keys
+pub fn pdf_metadata(doc: &lopdf::Document) -> BTreeMap<String, String> {
+    let mut metadata = BTreeMap::new();
+    let Ok(info) = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|info| doc.dereference(info))
+        .and_then(|(_, info)| info.as_dict())
+    else {
+        return metadata;
+    };
+
+    for (field, key) in [
+        (&b"Author"[..], "author"),
+        (b"Title", "title"),
+        (b"CreationDate", "created"),
+    ] {
+        let Ok(value) = info
+            .get(field)
+            .and_then(|value| doc.dereference(value))
+            .and_then(|(_, value)| lopdf::decode_text_string(value))
+        else {
+            continue;
+        };
+        let value = if key == "created" {
+            parse_pdf_date(&value).unwrap_or(value)
+        } else {
+            value.trim().to_string()
+        };
+        if !value.is_empty() {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+    metadata
+}
+
+/// Split the YAML front matter off a Markdown document
+/// Only simple "key: value" lines are read; lists and nested values are kept as text
+/// Parameters:
+///   - content: The full Markdown text
+///
+/// Returns: (front matter keys, remaining body)
+pub fn split_front_matter(content: &str) -> (BTreeMap<String, String>, &str) {
+    let mut metadata = BTreeMap::new();
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (metadata, content);
+    };
+
+    // The front matter ends at the next line consisting only of "---" (or "...")
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed == "---" || trimmed == "..." {
+            return (metadata, &rest[offset + line.len()..]);
+        }
+        if let Some((key, value)) = line.split_once(':')
+            && !line.starts_with([' ', '\t', '-'])
+        {
+            let key = key.trim().to_lowercase();
+            let value = value.trim().trim_matches(['"', '\'']).to_string();
+            if !key.is_empty() && !value.is_empty() && !FILE_KEYS.contains(&key.as_str()) {
+                metadata.insert(key, value);
+            }
+        }
+        offset += line.len();
+    }
+
+    // No closing marker: this was not front matter after all
+    (BTreeMap::new(), content)
+}
+
+/// Convert seconds since the Unix epoch into "YYYY-MM-DDTHH:MM:SSZ"
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Convert a PDF date ("D:20250101120000+01'00'") into "YYYY-MM-DDTHH:MM:SS"
+fn parse_pdf_date(value: &str) -> Option<String> {
+    let digits: String = value
+        .trim()
+        .trim_start_matches("D:")
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    if digits.len() < 4 {
+        return None;
+    }
+
+    // Missing parts default to the start of the year/month/day
+    let part = |range: std::ops::Range<usize>, default: &'static str| {
+        digits.get(range).unwrap_or(default).to_string()
+    };
+    let date = format!(
+        "{}-{}-{}",
+        part(0..4, ""),
+        part(4..6, "01"),
+        part(6..8, "01")
+    );
+    if digits.len() <= 8 {
+        return Some(date);
+    }
+    Some(format!(
+        "{}T{}:{}:{}",
+        date,
+        part(8..10, "00"),
+        part(10..12, "00"),
+        part(12..14, "00")
+    ))
+}
+
+// Conditions a document's metadata must meet for its chunks to be searched
+#[derive(Debug, Default, Clone)]
+pub struct MetadataFilter {
+    conditions: Vec<(String, Regex)>, // (metadata key, pattern the value must match)
+    since: Option<(u32, u32, u32)>,   // Earliest document date, as (year, month, day)
+}
+
+// A calendar date ("YYYY-MM-DD", optionally followed by a time) at the start of a value
+const ISO_DATE: &str = r"^(\d{4})-(\d{2})-(\d{2})(?:$|[T ])";
+
+impl MetadataFilter {
+    /// Build a filter from command-line options
+    /// Parameters:
+    ///   - filters: "key=pattern" conditions; patterns may use '*', '**' and '?' wildcards
+    ///   - since: Earliest document date ("YYYY-MM-DD"), if any
+    ///
+    /// Returns: The filter, or an error describing the first malformed option
+    pub fn parse(filters: &[String], since: Option<&str>) -> Result<Self> {
+        let mut conditions = Vec::new();
+        for filter in filters {
+            let Some((key, pattern)) = filter.split_once('=') else {
+                bail!(
+                    "invalid filter '{}' (expected key=value, e.g. type=pdf)",
+                    filter
+                );
+            };
+            let key = key.trim().to_lowercase();
+            if key.is_empty() {
+                bail!("invalid filter '{}' (missing metadata key)", filter);
+            }
+            conditions.push((key, glob_to_regex(pattern.trim())?));
+        }
+
+        let since = match since {
+            Some(date) => match parse_iso_date(date.trim()) {
+                Some(date) => Some(date),
+                None => bail!("invalid date '{}' for --since (expected YYYY-MM-DD)", date),
+            },
+            None => None,
+        };
+
+        Ok(Self { conditions, since })
+    }
+
+    /// Check whether the filter has no conditions at all
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty() && self.since.is_none()
+    }
+
+    /// Check whether a document meets every condition of the filter
+    /// Parameters:
+    ///   - document: The document to test
+    ///
+    /// Returns: true if the document's chunks should be searched
+    pub fn matches(&self, document: &Document) -> bool {
+        let conditions_met = self.conditions.iter().all(|(key, pattern)| {
+            document
+                .metadata
+                .get(key)
+                .is_some_and(|value| pattern.is_match(value))
+        });
+
+        // The document date is its creation date when known, otherwise its modification time
+        // (front-matter dates that are not ISO dates are skipped rather than guessed at)
+        let recent_enough = self.since.is_none_or(|since| {
+            ["created", "date", "modified"]
+                .iter()
+                .filter_map(|key| document.metadata.get(*key))
+                .find_map(|date| parse_iso_date(date))
+                .is_some_and(|date| date >= since)
+        });
+
+        conditions_met && recent_enough
+    }
+}
+
+/// Parse the calendar date at the start of an ISO 8601 value ("2025-01-31" or "2025-01-31T12:00:00Z")
+/// Parameters:
+///   - value: The date or timestamp text
+///
+/// Returns: (year, month, day), or None if the value does not start with a valid ISO date
+fn parse_iso_date(value: &str) -> Option<(u32, u32, u32)> {
+    let captures = Regex::new(ISO_DATE).ok()?.captures(value)?;
+    let part = |i: usize| captures[i].parse::<u32>().ok();
+    let (year, month, day) = (part(1)?, part(2)?, part(3)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((year, month, day))
+}
+
+/// Translate a glob pattern into an anchored, case-insensitive regular expression
+/// '**' matches across '/' separators, '*' and '?' do not
+pub(crate) fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("(?i)^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).map_err(|e| anyhow!("invalid pattern '{}': {}", pattern, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A document with the given metadata
+    fn document(metadata: &[(&str, &str)]) -> Document {
+        Document {
+            id: "doc".to_string(),
+            title: "lease".to_string(),
+            content: String::new(),
+            source: "data/contracts/lease.pdf".to_string(),
+            chunk_index: 0,
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// A filter with only a --since date
+    fn since(date: &str) -> MetadataFilter {
+        MetadataFilter::parse(&[], Some(date)).unwrap()
+    }
+
+    #[test]
+    fn iso_dates_are_read_with_or_without_a_time() {
+        assert_eq!(parse_iso_date("2025-06-01"), Some((2025, 6, 1)));
+        assert_eq!(parse_iso_date("2025-06-01T09:30:00Z"), Some((2025, 6, 1)));
+        assert_eq!(parse_iso_date("2025-06-01 09:30"), Some((2025, 6, 1)));
+        assert_eq!(parse_iso_date("2025-13-01"), None);
+        assert_eq!(parse_iso_date("2025-06-001"), None);
+        assert_eq!(parse_iso_date("June 2025"), None);
+        assert_eq!(parse_iso_date("01/06/2025"), None);
+    }
+
+    #[test]
+    fn since_must_be_an_iso_date() {
+        assert!(MetadataFilter::parse(&[], Some(" 2025-01-31 ")).is_ok());
+        let error = MetadataFilter::parse(&[], Some("31.01.2025"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("expected YYYY-MM-DD"), "{}", error);
+    }
+
+    #[test]
+    fn since_compares_calendar_dates() {
+        // A creation time later on the same day counts as that day
+        let doc = document(&[("created", "2025-06-01T23:59:59")]);
+        assert!(since("2025-06-01").matches(&doc));
+        assert!(!since("2025-06-02").matches(&doc));
+
+        // A front-matter date that is not an ISO date falls back to the modification time
+        let doc = document(&[("date", "June 2025"), ("modified", "2024-12-31T10:00:00Z")]);
+        assert!(since("2024-12-31").matches(&doc));
+        assert!(!since("2025-01-01").matches(&doc));
+
+        // Without any date the document is left out
+        assert!(!since("2000-01-01").matches(&document(&[])));
+    }
+
+    #[test]
+    fn globs_match_within_or_across_path_segments() {
+        let glob = |pattern: &str| glob_to_regex(pattern).unwrap();
+        assert!(glob("contracts/*.pdf").is_match("contracts/lease.pdf"));
+        assert!(glob("contracts/*.pdf").is_match("Contracts/LEASE.PDF"));
+        assert!(!glob("contracts/*.pdf").is_match("contracts/2024/lease.pdf"));
+        assert!(glob("contracts/**.pdf").is_match("contracts/2024/lease.pdf"));
+        assert!(glob("**/lease.pdf").is_match("contracts/2024/lease.pdf"));
+        assert!(glob("lease-?.pdf").is_match("lease-2.pdf"));
+        assert!(!glob("lease-?.pdf").is_match("lease-12.pdf"));
+        assert!(!glob("pdf").is_match("lease.pdf"));
+        assert!(glob("a+b (1).txt").is_match("a+b (1).txt"));
+    }
+
+    #[test]
+    fn filters_need_a_key_and_every_condition_met() {
+        assert!(MetadataFilter::parse(&["pdf".to_string()], None).is_err());
+        assert!(MetadataFilter::parse(&["=pdf".to_string()], None).is_err());
+
+        let filters = ["Type=PDF".to_string(), "path=contracts/**".to_string()];
+        let filter = MetadataFilter::parse(&filters, None).unwrap();
+        assert!(filter.matches(&document(&[
+            ("type", "pdf"),
+            ("path", "contracts/lease.pdf")
+        ])));
+        assert!(!filter.matches(&document(&[("type", "pdf"), ("path", "notes.md")])));
+        assert!(!filter.matches(&document(&[("type", "pdf")])));
+    }
+}
@@ -0,0 +1,101 @@
+// This module extracts the text of PDFs page by page and maps chunks of paged
+// documents back onto their page numbers. Chunkers rewrite whitespace and
+// punctuation, so chunks are located in the page text by their sequence of
+// normalised words rather than by byte offsets
+
+use crate::rag::DocumentChunk;
+use anyhow::Result;
+use pdf_extract::{PlainTextOutput, output_doc_page};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+// Number of leading words used to find where a chunk starts in the document
+const ANCHOR_WORDS: usize = 8;
+
+/// Load a PDF and extract the text of each of its pages
+/// The parsed document is returned too, so its info dictionary can be read without
+/// loading the file a second time
+/// Parameters:
+///   - path: Path to the PDF file
+///
+/// Returns: (parsed document, text of each page in order, numbers of the pages that
+/// could not be read)
+pub fn load_pdf(path: &Path) -> Result<(lopdf::Document, Vec<String>, Vec<u32>)> {
+    let mut document = lopdf::Document::load(path)?;
+    // Documents encrypted with an empty user password can still be read
+    if document.is_encrypted() {
+        document.decrypt("")?;
+    }
+
+    // A page that cannot be read (or crashes the parser) is left empty, so the
+    // pages after it keep their page numbers
+    let mut pages = Vec::new();
+    let mut unreadable = Vec::new();
+    for page_num in document.get_pages().into_keys() {
+        let page = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut text = String::new();
+            output_doc_page(&document, &mut PlainTextOutput::new(&mut text), page_num).map(|_| text)
+        }));
+        match page {
+            Ok(Ok(text)) => pages.push(text),
+            _ => {
+                unreadable.push(page_num);
+                pages.push(String::new());
+            }
+        }
+    }
+    Ok((document, pages, unreadable))
+}
+
+/// Record the page range of each chunk of a paged document
+/// Parameters:
+///   - chunks: The document's chunks, in document order
+///   - pages: Text of each page, in order (page 1 first)
+pub fn assign_pages(chunks: &mut [DocumentChunk], pages: &[String]) {
+    // Normalised words of the whole document, with the page number of each word
+    let mut words = Vec::new();
+    let mut page_of = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        for word in normalised_words(page) {
+            words.push(word);
+            page_of.push(i + 1);
+        }
+    }
+    if words.is_empty() {
+        return;
+    }
+
+    // Chunks appear in order; with overlap a chunk may start before the previous one ended
+    let mut cursor = 0;
+    for chunk in chunks.iter_mut() {
+        let chunk_words = normalised_words(&chunk.content);
+        if chunk_words.is_empty() {
+            continue;
+        }
+
+        let anchor = &chunk_words[..chunk_words.len().min(ANCHOR_WORDS)];
+        let Some(start) = (cursor..words.len())
+            .find(|&i| words[i..].starts_with(anchor))
+            .or_else(|| (0..cursor).find(|&i| words[i..].starts_with(anchor)))
+        else {
+            continue;
+        };
+
+        let end = (start + chunk_words.len()).min(words.len()) - 1;
+        chunk.pages = Some((page_of[start], page_of[end]));
+        cursor = start;
+    }
+}
+
+/// Split text into lowercase alphanumeric words, dropping pure punctuation
+fn normalised_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
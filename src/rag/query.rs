@@ -0,0 +1,486 @@
+// This module implements the query language of search_query(), used by --rag search
+// and, with --query-syntax, for --use-local questions (plain questions are searched word by word)
+//   - words are matched on their own, as before: engine noise
+//   - quoted phrases match consecutive words: "quantum quasar"
+//   - +term must be present and -term must not: +engine -diesel
+//   - OR joins terms into one group, any of which matches: diesel OR petrol
+//     (a + or - before the first term applies to the whole group)
+//   - title:pattern and source:pattern restrict the documents searched. Patterns may use
+//     '*', '**' and '?' wildcards; a title without wildcards matches any title containing it,
+//     and a source pattern is matched against the path relative to the data directory
+//     (any other "name:" is an ordinary word, so "http://example.com" can be searched for)
+// Phrases are matched using the word positions stored in the index (positional postings)
+
+use crate::rag::metadata::glob_to_regex;
+use crate::rag::search::query_terms;
+use crate::rag::{Document, RagSystem};
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+
+// Whether a clause may, must or must not match a chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Occur {
+    Should,  // Optional; matching it adds to the relevance
+    Must,    // Required (+term, and field restrictions)
+    MustNot, // Excluded (-term)
+}
+
+// Document field a restriction applies to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Title,  // Document title (the file name)
+    Source, // Source path, relative to the data directory
+}
+
+// A single term of a query
+#[derive(Debug, Clone)]
+pub enum Term {
+    Word(String),        // A normalised word
+    Phrase(Vec<String>), // Normalised words that must appear one after another
+    Field(Field, Regex), // A pattern the document's title or source must match
+}
+
+// Terms joined by OR, any of which satisfies the clause
+#[derive(Debug, Clone)]
+pub struct Clause {
+    pub occur: Occur,            // Whether the clause may, must or must not match
+    pub alternatives: Vec<Term>, // The terms of the OR group (a single term without OR)
+}
+
+// A parsed search query
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+// A term as written in the query, before normalisation
+struct Atom {
+    column: usize, // Position of the term in the query, counted in characters from 1
+    modifier: Option<char>, // '+' or '-' written before the term
+    field: Option<Field>, // Field named before a ':', if any
+    value: String, // The word, phrase or pattern
+}
+
+// A lexical element of a query
+enum Token {
+    Atom(Atom),
+    Or(usize), // The OR keyword, with its column
+}
+
+impl Query {
+    /// Parse a query written in the query language
+    /// Parameters:
+    ///   - text: The query, e.g. "+engine -diesel \"oil change\" source:manuals/*"
+    ///
+    /// Returns: The parsed query, or an error saying what is wrong and where
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut tokens = tokenize(text)?.into_iter().peekable();
+        let mut clauses = Vec::new();
+
+        while let Some(token) = tokens.next() {
+            let atom = match token {
+                Token::Atom(atom) => atom,
+                Token::Or(column) => {
+                    bail!(
+                        "OR at character {} must be written between two terms, e.g. diesel OR petrol",
+                        column
+                    )
+                }
+            };
+            let occur = match atom.modifier {
+                Some('+') => Occur::Must,
+                Some(_) => Occur::MustNot,
+                None => Occur::Should,
+            };
+            let mut alternatives: Vec<Term> = atom_term(&atom)?.into_iter().collect();
+
+            // Collect the rest of an OR group
+            while let Some(Token::Or(column)) = tokens.peek() {
+                let column = *column;
+                tokens.next();
+                let atom = match tokens.next() {
+                    Some(Token::Atom(atom)) => atom,
+                    _ => bail!(
+                        "OR at character {} must be followed by a term, e.g. diesel OR petrol",
+                        column
+                    ),
+                };
+                if let Some(modifier) = atom.modifier {
+                    bail!(
+                        "'{}' at character {} is inside an OR group; put + or - before the group's first term to apply it to the whole group",
+                        modifier,
+                        atom.column
+                    );
+                }
+                alternatives.extend(atom_term(&atom)?);
+            }
+
+            if alternatives.is_empty() {
+                continue;
+            }
+
+            // A field restriction without + or - still restricts the search
+            let restriction_only = alternatives
+                .iter()
+                .all(|term| matches!(term, Term::Field(..)));
+            let occur = if occur == Occur::Should && restriction_only {
+                Occur::Must
+            } else {
+                occur
+            };
+            clauses.push(Clause {
+                occur,
+                alternatives,
+            });
+        }
+
+        let query = Self { clauses };
+        if !query.clauses.is_empty() && query.terms().is_empty() {
+            bail!(
+                "the query has no words or phrases to search for, only restrictions or excluded terms"
+            );
+        }
+        Ok(query)
+    }
+
+    /// Treat text as plain words, without any query syntax
+    /// Used for queries written by a model (paraphrases, hypothetical answers), whose
+    /// quotes and hyphens are not meant as operators
+    /// Parameters:
+    ///   - text: The text to search for
+    ///
+    /// Returns: A query with one optional clause per word
+    pub fn plain(text: &str) -> Self {
+        let clauses = query_terms(text)
+            .into_iter()
+            .map(|word| Clause {
+                occur: Occur::Should,
+                alternatives: vec![Term::Word(word)],
+            })
+            .collect();
+        Self { clauses }
+    }
+
+    /// Add the required, excluded and field clauses of another query to this one
+    /// Parameters:
+    ///   - other: The query whose restrictions apply (e.g. the question a paraphrase came from)
+    ///
+    /// Returns: This query with the other's restrictions added
+    pub fn restricted_by(mut self, other: &Query) -> Self {
+        self.clauses.extend(
+            other
+                .clauses
+                .iter()
+                .filter(|clause| clause.occur != Occur::Should)
+                .cloned(),
+        );
+        self
+    }
+
+    /// The words the query searches for (not the excluded ones), for highlighting
+    /// Returns: The normalised words of the optional and required words and phrases
+    pub fn terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        for clause in self.clauses.iter().filter(|c| c.occur != Occur::MustNot) {
+            for term in &clause.alternatives {
+                match term {
+                    Term::Word(word) => terms.push(word.clone()),
+                    Term::Phrase(words) => terms.extend(words.iter().cloned()),
+                    Term::Field(..) => {}
+                }
+            }
+        }
+        terms
+    }
+}
+
+impl Term {
+    /// Check whether a field restriction holds for a document
+    /// Returns: true if the document matches (always false for words and phrases)
+    pub fn matches_document(&self, document: &Document) -> bool {
+        match self {
+            Term::Field(Field::Title, pattern) => pattern.is_match(&document.title),
+            Term::Field(Field::Source, pattern) => {
+                document
+                    .metadata
+                    .get("path")
+                    .is_some_and(|path| pattern.is_match(path))
+                    || pattern.is_match(&document.source)
+            }
+            Term::Word(_) | Term::Phrase(_) => false,
+        }
+    }
+}
+
+impl RagSystem {
+    /// Find the chunks containing a word or phrase
+    /// Parameters:
+    ///   - term: The word or phrase to look up
+    ///
+    /// Returns: Number of occurrences per chunk index, or None for field restrictions
+    pub(crate) fn term_matches(&self, term: &Term) -> Option<HashMap<usize, usize>> {
+        match term {
+            Term::Word(word) => Some(
+                self.word_index
+                    .get(word)
+                    .map(|postings| {
+                        postings
+                            .iter()
+                            .map(|posting| (posting.chunk, posting.positions.len()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            Term::Phrase(words) => Some(self.phrase_matches(words)),
+            Term::Field(..) => None,
+        }
+    }
+
+    /// Find the chunks where the words of a phrase appear one after another
+    /// Parameters:
+    ///   - words: The normalised words of the phrase (at least one)
+    ///
+    /// Returns: Number of occurrences of the phrase per chunk index
+    fn phrase_matches(&self, words: &[String]) -> HashMap<usize, usize> {
+        let mut matches = HashMap::new();
+        let Some(first) = self.word_index.get(&words[0]) else {
+            return matches;
+        };
+
+        // Positions of the following words in each chunk
+        let mut following: Vec<HashMap<usize, &Vec<u32>>> = Vec::new();
+        for word in &words[1..] {
+            let Some(postings) = self.word_index.get(word) else {
+                return matches;
+            };
+            following.push(
+                postings
+                    .iter()
+                    .map(|posting| (posting.chunk, &posting.positions))
+                    .collect(),
+            );
+        }
+
+        for posting in first {
+            let count = posting
+                .positions
+                .iter()
+                .filter(|&&start| {
+                    following.iter().zip(1..).all(|(positions, offset)| {
+                        positions.get(&posting.chunk).is_some_and(|positions| {
+                            positions.binary_search(&(start + offset)).is_ok()
+                        })
+                    })
+                })
+                .count();
+            if count > 0 {
+                matches.insert(posting.chunk, count);
+            }
+        }
+        matches
+    }
+}
+
+/// Get the words to highlight for a query, e.g. in snippets and excerpts
+/// Parameters:
+///   - query: The query as written
+///
+/// Returns: The words it searches for (all its words if it is not a valid query)
+pub fn highlight_terms(query: &str) -> Vec<String> {
+    Query::parse(query)
+        .map(|query| query.terms())
+        .unwrap_or_else(|_| query_terms(query))
+}
+
+/// Split a query into terms and OR keywords
+/// Returns: The tokens, or an error for an unterminated phrase or a misplaced + or -
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let column = i + 1;
+
+        // A + or - must be attached to the term it applies to
+        let modifier = match chars[i] {
+            '+' | '-' => {
+                let modifier = chars[i];
+                i += 1;
+                if i == chars.len() || chars[i].is_whitespace() {
+                    bail!(
+                        "'{}' at character {} must be followed directly by a term, e.g. {}engine",
+                        modifier,
+                        column,
+                        modifier
+                    );
+                }
+                Some(modifier)
+            }
+            _ => None,
+        };
+
+        // The term runs to the next space, unless it is (or ends in) a quoted phrase
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '"' {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+
+        // "field:" followed by a value, e.g. title:astrobill or source:"user guides/*"
+        // (other names, as in "http://example.com" or "note:", are part of an ordinary word)
+        let mut field = None;
+        let mut value = word.clone();
+        if let Some((name, rest)) = word.split_once(':')
+            && (!rest.is_empty() || chars.get(i) == Some(&'"'))
+        {
+            field = match name.to_lowercase().as_str() {
+                "title" => Some(Field::Title),
+                "source" => Some(Field::Source),
+                _ => None,
+            };
+            if field.is_some() {
+                value = rest.to_string();
+            }
+        }
+
+        let quoted = i < chars.len() && chars[i] == '"' && (word.is_empty() || field.is_some());
+        if quoted {
+            let open = i;
+            i += 1;
+            let phrase_start = i;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i == chars.len() {
+                bail!(
+                    "the quote at character {} is never closed (phrases are written \"like this\")",
+                    open + 1
+                );
+            }
+            value = chars[phrase_start..i].iter().collect();
+            i += 1;
+        }
+
+        if modifier.is_none() && field.is_none() && !quoted && word == "OR" {
+            tokens.push(Token::Or(column));
+        } else {
+            tokens.push(Token::Atom(Atom {
+                column,
+                modifier,
+                field,
+                value,
+            }));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Turn a term as written into a search term
+/// Returns: The term, None if it has no searchable words (e.g. punctuation only), or an
+/// error for an empty or invalid field pattern
+fn atom_term(atom: &Atom) -> Result<Option<Term>> {
+    if let Some(field) = atom.field {
+        let value = atom.value.trim();
+        if value.is_empty() {
+            bail!("the field at character {} has no value", atom.column);
+        }
+        // A title without wildcards matches any title containing it
+        let pattern = if field == Field::Title && !value.contains(['*', '?']) {
+            format!("*{}*", value)
+        } else {
+            value.to_string()
+        };
+        return Ok(Some(Term::Field(field, glob_to_regex(&pattern)?)));
+    }
+
+    // Only a quoted value can hold several words
+    let mut words = query_terms(&atom.value);
+    Ok(match words.len() {
+        0 => None,
+        1 => words.pop().map(Term::Word),
+        _ => Some(Term::Phrase(words)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The occurrence and word of each single-word clause of a query
+    fn words(query: &Query) -> Vec<(Occur, String)> {
+        query
+            .clauses
+            .iter()
+            .filter_map(|clause| match &clause.alternatives[..] {
+                [Term::Word(word)] => Some((clause.occur, word.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plain_questions_are_searched_word_by_word() {
+        for question in [
+            "see http://x",
+            "a 12\" pipe",
+            "OR what about petrol",
+            "clearance of -20 mm",
+        ] {
+            let query = Query::plain(question);
+            assert!(
+                query
+                    .clauses
+                    .iter()
+                    .all(|clause| clause.occur == Occur::Should),
+                "{}",
+                question
+            );
+            assert_eq!(query.terms(), query_terms(question), "{}", question);
+        }
+        assert!(
+            words(&Query::plain("clearance of -20 mm")).contains(&(Occur::Should, "20".into()))
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_ordinary_words() {
+        let query = Query::parse("see http://example.com note: it").unwrap();
+        assert_eq!(query.terms(), ["see", "httpexamplecom", "note", "it"]);
+
+        let query = Query::parse("reset title:astrobill").unwrap();
+        assert!(matches!(
+            query.clauses[1].alternatives[..],
+            [Term::Field(Field::Title, _)]
+        ));
+    }
+
+    #[test]
+    fn query_syntax_errors_give_their_position() {
+        let error = Query::parse("a 12\" pipe").unwrap_err().to_string();
+        assert!(error.contains("quote at character 5"), "{}", error);
+
+        let error = Query::parse("OR what about petrol")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("OR at character 1"), "{}", error);
+    }
+
+    #[test]
+    fn minus_excludes_a_term_only_in_the_query_syntax() {
+        let query = Query::parse("clearance -20").unwrap();
+        assert_eq!(
+            words(&query),
+            [
+                (Occur::Should, "clearance".to_string()),
+                (Occur::MustNot, "20".to_string())
+            ]
+        );
+    }
+}
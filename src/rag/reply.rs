@@ -0,0 +1,41 @@
+// This module cleans up the replies of Ollama models for the RAG features that read them
+// "Thinking" models wrap their reasoning in <think>...</think> before the actual reply,
+// and answers cite the numbered context chunks with markers such as [1], [2, 3] or [2-4]
+
+use regex::Regex;
+
+// Reasoning block of a "thinking" model
+const THINKING_PATTERN: &str = r"(?s)<think>.*?</think>";
+
+// Citation marker; the first group holds the cited numbers, e.g. "1, 4" or "2-3"
+const CITATION_PATTERN: &str = r"\[\s*(\d+(?:\s*[,;\-–]\s*\d+)*)\s*\]";
+
+/// Remove the reasoning of "thinking" models from a reply
+/// Parameters:
+///   - reply: The model's reply
+///
+/// Returns: The reply without any <think>...</think> blocks
+pub fn strip_thinking(reply: &str) -> String {
+    Regex::new(THINKING_PATTERN)
+        .unwrap()
+        .replace_all(reply, "")
+        .to_string()
+}
+
+/// Build the regular expression that finds citation markers
+/// Returns: A regex whose first capture group holds the cited numbers
+pub fn citation_regex() -> Regex {
+    Regex::new(CITATION_PATTERN).unwrap()
+}
+
+/// Remove citation markers, with the whitespace before them, from a text
+/// Parameters:
+///   - text: Answer text, e.g. "The notice period is one month [2]."
+///
+/// Returns: The text without markers, e.g. "The notice period is one month."
+pub fn strip_citations(text: &str) -> String {
+    Regex::new(&format!(r"\s*{}", CITATION_PATTERN))
+        .unwrap()
+        .replace_all(text, "")
+        .to_string()
+}
@@ -0,0 +1,113 @@
+// This module records what happened to every file during a build
+// A build no longer stops at the first unreadable file: successes, skipped files,
+// warnings and failures are collected into a report that is printed at the end
+// and saved as JSON next to the index
+
+use crate::colour_print;
+use crate::rag::metadata::format_timestamp;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// File name of the build report inside the index directory
+pub const REPORT_FILE: &str = "build_report.json";
+
+// A file mentioned in the report, with the reason it is there
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileNote {
+    pub path: String,    // Source file path (or directory, for unreadable entries)
+    pub message: String, // Why the file was skipped, warned about or failed
+}
+
+// Outcome of a single build
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildReport {
+    pub built_at: String,       // When the build finished, as "YYYY-MM-DDTHH:MM:SSZ"
+    pub added: Vec<String>,     // Files indexed for the first time
+    pub changed: Vec<String>,   // Files re-indexed because their contents changed
+    pub removed: Vec<String>,   // Files dropped because they no longer exist
+    pub unchanged: usize,       // Files reused from the existing index
+    pub skipped: Vec<FileNote>, // Files that were deliberately not indexed
+    pub warnings: Vec<FileNote>, // Files that were indexed but may not be useful
+    pub failures: Vec<FileNote>, // Files that could not be indexed because of an error
+    pub document_count: usize,  // Documents in the index after the build
+    pub chunk_count: usize,     // Chunks in the index after the build
+}
+
+impl BuildReport {
+    /// Note a file that was deliberately not indexed
+    pub fn skip(&mut self, path: &Path, message: impl Into<String>) {
+        self.skipped.push(note(path, message));
+    }
+
+    /// Note a file that was indexed but may not be useful
+    pub fn warn(&mut self, path: &Path, message: impl Into<String>) {
+        self.warnings.push(note(path, message));
+    }
+
+    /// Note a file that could not be indexed
+    pub fn fail(&mut self, path: &Path, message: impl Into<String>) {
+        self.failures.push(note(path, message));
+    }
+
+    /// Record the final size of the index and the time the build finished
+    pub fn finish(&mut self, document_count: usize, chunk_count: usize) {
+        self.document_count = document_count;
+        self.chunk_count = chunk_count;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.built_at = format_timestamp(now);
+    }
+
+    /// Print a summary of the build, listing every skipped, warned and failed file
+    pub fn print(&self) {
+        colour_print(
+            &format!(
+                "\t {} new, {} changed, {} removed, {} unchanged files",
+                self.added.len(),
+                self.changed.len(),
+                self.removed.len(),
+                self.unchanged
+            ),
+            "cyan",
+        );
+
+        for (label, notes, colour) in [
+            ("Skipped", &self.skipped, "yellow"),
+            ("Warnings for", &self.warnings, "yellow"),
+            ("Failed to index", &self.failures, "red"),
+        ] {
+            if notes.is_empty() {
+                continue;
+            }
+            colour_print(&format!("\t {} {} files:", label, notes.len()), colour);
+            for note in notes {
+                colour_print(&format!("\t   {} ({})", note.path, note.message), colour);
+            }
+        }
+    }
+
+    /// Save the report as pretty-printed JSON
+    /// Parameters:
+    ///   - path: Location of the report file
+    ///
+    /// Returns: Result indicating success or failure
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+/// Build a report entry for a file
+fn note(path: &Path, message: impl Into<String>) -> FileNote {
+    FileNote {
+        path: path.display().to_string(),
+        message: message.into(),
+    }
+}
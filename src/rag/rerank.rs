@@ -0,0 +1,157 @@
+// This module re-ranks retrieved candidates with a local Ollama model
+// The lexical TF-IDF ranking is only a rough signal, so a larger set of candidates is
+// retrieved and the model is asked to rate each one's relevance to the question.
+// Ratings are cached per (model, prompt, query, chunk) in the index directory, so asking
+// the same question again does not call the model again
+
+use crate::colour_print;
+use crate::rag::reply::strip_thinking;
+use crate::rag::{DocumentChunk, RagSystem, stable_id};
+use anyhow::{Result, anyhow};
+use ollama_rs::Ollama;
+use ollama_rs::generation::completion::request::GenerationRequest;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// File name of the rating cache inside the index directory
+pub const RERANK_CACHE_FILE: &str = "rerank_cache.json";
+
+// Number of candidates retrieved for re-ranking unless configured otherwise
+pub const DEFAULT_CANDIDATES: usize = 30;
+
+// Prompt used to rate a candidate; {question} and {passage} are filled in
+pub const DEFAULT_PROMPT: &str = "Rate how relevant the passage is to the question, on a scale from 0 (irrelevant) to 10 (directly answers it). Reply with the number only.\n\nQuestion: {question}\n\nPassage: {passage}\n\nRelevance (0-10):";
+
+// Highest rating the model is asked to give
+const MAX_RATING: f32 = 10.0;
+
+// How candidates are re-ranked
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerankSettings {
+    pub model: String,     // Ollama model that rates the candidates
+    pub prompt: String,    // Prompt template with {question} and {passage} placeholders
+    pub candidates: usize, // Number of candidates to retrieve and rate
+}
+
+// Cached ratings, keyed by a hash of the model, prompt, query and chunk ID
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RerankCache {
+    ratings: BTreeMap<String, f32>, // Rating from 0 to 1
+}
+
+impl RerankCache {
+    /// Load the cache, starting afresh if it is missing or unreadable
+    fn load(path: &Path) -> Self {
+        File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the cache as JSON
+    fn save(&self, path: &Path) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+}
+
+impl RagSystem {
+    /// Re-rank retrieved candidates by their relevance as rated by an Ollama model
+    /// Parameters:
+    ///   - query: The user's question
+    ///   - candidates: Retrieved (score, chunk) pairs, best first
+    ///   - settings: The rating model, prompt template and candidate count
+    ///
+    /// Returns: The candidates rated 0–1 and sorted best first, followed by any the model did not
+    /// rate (with their retrieval scores, in their original order), or an error if the model failed
+    pub async fn rerank<'a>(
+        &self,
+        query: &str,
+        candidates: &[(f32, &'a DocumentChunk)],
+        settings: &RerankSettings,
+    ) -> Result<Vec<(f32, &'a DocumentChunk)>> {
+        let cache_path = self.agentic_dir.join(RERANK_CACHE_FILE);
+        let mut cache = RerankCache::load(&cache_path);
+        let ollama = Ollama::new("http://localhost".to_string(), 11434);
+
+        let mut rated = Vec::new();
+        let mut unrated = Vec::new();
+        let mut asked = 0;
+        let mut new_ratings = 0;
+        for &(score, chunk) in candidates {
+            let key = stable_id(&[&settings.model, &settings.prompt, query, &chunk.id]);
+            let rating = match cache.ratings.get(&key) {
+                Some(&rating) => rating,
+                None => {
+                    asked += 1;
+                    let prompt = settings
+                        .prompt
+                        .replace("{question}", query)
+                        .replace("{passage}", &chunk.content);
+                    let response = match ollama
+                        .generate(GenerationRequest::new(settings.model.clone(), prompt))
+                        .await
+                    {
+                        Ok(response) => response,
+                        Err(e) => {
+                            // Keep the ratings collected so far before giving up
+                            if new_ratings > 0 {
+                                cache.save(&cache_path)?;
+                            }
+                            return Err(anyhow!("model '{}' failed: {}", settings.model, e));
+                        }
+                    };
+
+                    // Answers that are not a rating leave the chunk unrated; only real
+                    // ratings are cached, so such a chunk is rated again next time
+                    match parse_rating(&response.response) {
+                        Some(rating) => {
+                            cache.ratings.insert(key, rating);
+                            new_ratings += 1;
+                            rating
+                        }
+                        None => {
+                            unrated.push((score, chunk));
+                            continue;
+                        }
+                    }
+                }
+            };
+            rated.push((rating, chunk));
+        }
+
+        if new_ratings > 0 {
+            cache.save(&cache_path)?;
+        }
+        colour_print(
+            &format!(
+                "\t Re-ranked {} candidates with {} ({} cached)",
+                candidates.len(),
+                settings.model,
+                candidates.len() - asked
+            ),
+            "cyan",
+        );
+
+        // Stable sort, so equally rated candidates keep their lexical order; retrieval scores
+        // are on another scale, so unrated candidates follow all rated ones in their own order
+        rated.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        rated.extend(unrated);
+        Ok(rated)
+    }
+}
+
+/// Read the rating from a model's reply, e.g. "7" or "Relevance: 8/10"
+/// The reasoning of "thinking" models (<think>...</think>) is skipped
+/// Returns: The rating scaled to 0–1, or None if the reply has no number
+fn parse_rating(reply: &str) -> Option<f32> {
+    let reply = strip_thinking(reply);
+    let re = Regex::new(r"\d+(?:\.\d+)?").unwrap();
+    let rating: f32 = re.find(&reply)?.as_str().parse().ok()?;
+    Some(rating.clamp(0.0, MAX_RATING) / MAX_RATING)
+}
@@ -0,0 +1,205 @@
+// This module transforms a question into several search queries before retrieval
+// Short or vague questions often share few words with the documents that answer them, so:
+//   - paraphrases: an Ollama model rewrites the question in different words
+//   - HyDE: the model writes a hypothetical answer passage, which is searched for instead
+//   - keywords: long questions are reduced to their content words (no model needed)
+// Each query is searched separately and the results are unioned, keeping each chunk's
+// best score. The original question is always searched as well
+
+use crate::colour_print;
+use crate::rag::metadata::MetadataFilter;
+use crate::rag::query::{Query, highlight_terms};
+use crate::rag::reply::strip_thinking;
+use crate::rag::{DocumentChunk, RagSystem};
+use anyhow::Result;
+use ollama_rs::Ollama;
+use ollama_rs::generation::completion::request::GenerationRequest;
+use std::collections::HashMap;
+
+// Questions with fewer words than this are already short enough to search as they are
+const KEYWORD_MIN_WORDS: usize = 6;
+
+// Common words left out of keyword queries
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "by", "can", "could", "did", "do", "does",
+    "for", "from", "had", "has", "have", "how", "i", "in", "is", "it", "its", "me", "my", "of",
+    "on", "or", "our", "please", "should", "tell", "that", "the", "their", "there", "these",
+    "this", "to", "was", "we", "were", "what", "when", "where", "which", "who", "why", "will",
+    "with", "would", "you", "your",
+];
+
+// Which query transformations to apply
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RewriteSettings {
+    pub paraphrases: usize, // Number of paraphrases of the question to search for (0: none)
+    pub hyde: bool,         // Whether to search for a hypothetical answer passage
+    pub keywords: bool,     // Whether to search for the keywords of long questions
+    pub model: String,      // Ollama model that writes the paraphrases and passage
+}
+
+// A search query derived from the question, and how it was derived
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedQuery {
+    pub kind: &'static str, // "original", "paraphrase", "hyde" or "keywords"
+    pub text: String,       // The query text
+}
+
+/// Derive the search queries for a question
+/// A transformation that fails (e.g. the model cannot be reached) is skipped with a warning
+/// Parameters:
+///   - question: The user's question
+///   - settings: Which transformations to apply and the model to use
+///
+/// Returns: The original question followed by its transformations
+pub async fn expand_query(question: &str, settings: &RewriteSettings) -> Vec<ExpandedQuery> {
+    let mut queries = vec![ExpandedQuery {
+        kind: "original",
+        text: question.to_string(),
+    }];
+
+    if settings.keywords
+        && let Some(keywords) = keywords(question)
+    {
+        queries.push(ExpandedQuery {
+            kind: "keywords",
+            text: keywords,
+        });
+    }
+
+    let ollama = Ollama::new("http://localhost".to_string(), 11434);
+
+    if settings.paraphrases > 0 {
+        let prompt = format!(
+            "Rewrite the following question in {} different ways, using different words where possible but keeping its meaning. \
+             Reply with one rewritten question per line and nothing else.\n\nQuestion: {}",
+            settings.paraphrases, question
+        );
+        match generate(&ollama, &settings.model, prompt).await {
+            Ok(reply) => {
+                // Models sometimes repeat the question or give more lines than asked for
+                let mut added = 0;
+                for line in reply.lines().map(clean_line).filter(|l| !l.is_empty()) {
+                    if added == settings.paraphrases {
+                        break;
+                    }
+                    if queries.iter().any(|q| q.text.eq_ignore_ascii_case(&line)) {
+                        continue;
+                    }
+                    queries.push(ExpandedQuery {
+                        kind: "paraphrase",
+                        text: line,
+                    });
+                    added += 1;
+                }
+            }
+            Err(e) => colour_print(&format!("\t Paraphrasing skipped, {}", e), "yellow"),
+        }
+    }
+
+    if settings.hyde {
+        let prompt = format!(
+            "Write a short passage of about 100 words that answers the following question, \
+             written as if it were taken from a document on the subject. Reply with the passage only.\n\nQuestion: {}",
+            question
+        );
+        match generate(&ollama, &settings.model, prompt).await {
+            Ok(passage) if !passage.trim().is_empty() => queries.push(ExpandedQuery {
+                kind: "hyde",
+                text: passage.trim().to_string(),
+            }),
+            Ok(_) => {}
+            Err(e) => colour_print(&format!("\t Hypothetical answer skipped, {}", e), "yellow"),
+        }
+    }
+
+    queries
+}
+
+/// Reduce a long question to its content words, e.g.
+/// "What is the notice period for ending the lease?" -> "notice period ending lease"
+/// Returns: The keywords, or None for short questions and questions that are all stopwords
+pub fn keywords(question: &str) -> Option<String> {
+    let words = highlight_terms(question);
+    if words.len() < KEYWORD_MIN_WORDS {
+        return None;
+    }
+    let mut keywords: Vec<String> = Vec::new();
+    for word in words {
+        if !STOPWORDS.contains(&word.as_str()) && !keywords.contains(&word) {
+            keywords.push(word);
+        }
+    }
+    (!keywords.is_empty()).then(|| keywords.join(" "))
+}
+
+impl RagSystem {
+    /// Search for several queries and union the results
+    /// The original question is searched as given (plain words, or parsed with the query
+    /// language); the derived queries are searched as plain words, with the question's
+    /// required, excluded and field terms applied to them
+    /// Parameters:
+    ///   - queries: The queries to search for, the original question first
+    ///   - original: The original question as it is to be searched
+    ///   - top_k: Maximum number of results to return
+    ///   - min_score: Minimum relevance of a result, applied before the top_k cut
+    ///   - filter: Metadata conditions a chunk's document must meet
+    ///
+    /// Returns: (relevance, chunk) pairs with each chunk's best relevance over the queries,
+    /// ranked by the best position any query's TF-IDF ranking gave the chunk
+    pub fn search_expanded(
+        &self,
+        queries: &[ExpandedQuery],
+        original: &Query,
+        top_k: usize,
+        min_score: f32,
+        filter: &MetadataFilter,
+    ) -> Vec<(f32, &DocumentChunk)> {
+        // Best relevance and best rank of each chunk, and the order in which chunks were
+        // first found
+        let mut best: HashMap<&str, (f32, usize, usize, &DocumentChunk)> = HashMap::new();
+        for query in queries {
+            let parsed = if query.kind == "original" {
+                original.clone()
+            } else {
+                Query::plain(&query.text).restricted_by(original)
+            };
+            let results = self.search_query(&parsed, top_k, min_score, filter);
+            for (rank, (relevance, chunk)) in results.into_iter().enumerate() {
+                let found = best.len();
+                let entry = best
+                    .entry(chunk.id.as_str())
+                    .or_insert((relevance, rank, found, chunk));
+                entry.0 = entry.0.max(relevance);
+                entry.1 = entry.1.min(rank);
+            }
+        }
+
+        // Sort by best rank; equal ranks keep the order the chunks were found in
+        let mut results: Vec<(f32, usize, usize, &DocumentChunk)> = best.into_values().collect();
+        results.sort_by_key(|&(_, rank, found, _)| (rank, found));
+        results
+            .into_iter()
+            .take(top_k)
+            .map(|(relevance, _, _, chunk)| (relevance, chunk))
+            .collect()
+    }
+}
+
+/// Ask the model for a completion, without the reasoning of "thinking" models
+async fn generate(ollama: &Ollama, model: &str, prompt: String) -> Result<String, String> {
+    ollama
+        .generate(GenerationRequest::new(model.to_string(), prompt))
+        .await
+        .map(|response| strip_thinking(&response.response))
+        .map_err(|e| format!("model '{}' failed: {}", model, e))
+}
+
+/// Strip list numbering, bullets and quotes from a line of the model's reply
+fn clean_line(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*'))
+        .trim()
+        .trim_matches('"')
+        .trim()
+        .to_string()
+}
@@ -1,13 +1,163 @@
 // This module contains search-related functionality for the RAG system
-// Currently, the main search logic is implemented in the search_local method
-// in the main rag.rs file, so this module is kept minimal for future extensions
-
-// Not much to see here yet, but this is where we can add search-related functionality
+// The TF-IDF scoring itself is implemented in the search_query method in rag.rs
+// (with the query language in query.rs);
+// this module turns its results into ranked hits with a location and a snippet
+// around the query terms, as shown by 'rag search'
 
 use crate::rag::RagSystem;
+use crate::rag::metadata::MetadataFilter;
+use crate::rag::query::Query;
+use anyhow::Result;
+use serde::Serialize;
+
+// Number of words shown in a search hit's snippet
+const SNIPPET_WORDS: usize = 30;
+
+// Number of words shown before the first highlighted match
+const SNIPPET_CONTEXT: usize = 5;
+
+// A ranked search result prepared for display or JSON output
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub rank: usize,                     // Position in the ranking, starting at 1
+    pub score: f32,                      // Relevance from 0 to 1 (share of the query matched)
+    pub chunk_id: String,                // ID of the matching chunk
+    pub document_id: String,             // ID of the chunk's document
+    pub title: String,                   // Document title
+    pub source: String,                  // Source file path
+    pub location: String,                // Pages and/or section, e.g. "p. 4 > Install"
+    pub snippet: String,                 // Words around the best match, with "…" where cut
+    pub highlights: Vec<(usize, usize)>, // Byte ranges of the query terms within the snippet
+}
 
-// Placeholder for future search enhancements
-// The main search functionality is currently in RagSystem::search_local()
 impl RagSystem {
-    // Future search improvements could be added here
+    /// Search the knowledge base and prepare the results for display
+    /// Parameters:
+    ///   - query: The search query string
+    ///   - top_k: Maximum number of hits to return
+    ///   - min_score: Minimum relevance of a hit, applied before the top_k cut
+    ///   - filter: Metadata conditions a chunk's document must meet
+    ///
+    /// Returns: Ranked hits with locations and highlighted snippets, or an error if the query
+    /// cannot be parsed
+    pub fn search_hits(
+        &self,
+        query: &str,
+        top_k: usize,
+        min_score: f32,
+        filter: &MetadataFilter,
+    ) -> Result<Vec<SearchHit>> {
+        let query = Query::parse(query)?;
+        let terms = query.terms();
+
+        let hits = self
+            .search_query(&query, top_k, min_score, filter)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (score, chunk))| {
+                let document = self.get_document_by_id(&chunk.document_id);
+
+                // Describe where the chunk sits in its document
+                let mut location = chunk.page_label().unwrap_or_default();
+                if !chunk.heading_path.is_empty() {
+                    if !location.is_empty() {
+                        location.push_str(" > ");
+                    }
+                    location.push_str(&chunk.heading_path);
+                }
+
+                let (snippet, highlights) = snippet(&chunk.content, &terms);
+                SearchHit {
+                    rank: i + 1,
+                    score,
+                    chunk_id: chunk.id.clone(),
+                    document_id: chunk.document_id.clone(),
+                    title: document.map(|d| d.title.clone()).unwrap_or_default(),
+                    source: document.map(|d| d.source.clone()).unwrap_or_default(),
+                    location,
+                    snippet,
+                    highlights,
+                }
+            })
+            .collect();
+        Ok(hits)
+    }
+}
+
+/// Normalise a query into its search terms (lowercase, alphanumeric only)
+pub fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(normalise)
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Normalise a word the same way the word index does
+fn normalise(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Pick the window of the chunk with the most query term matches
+/// Parameters:
+///   - content: Chunk text
+///   - terms: Normalised query terms
+///
+/// Returns: (snippet text, byte ranges of the matched words within it)
+pub(crate) fn snippet(content: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+    // Each word of the chunk, with whether it matches a query term
+    let words: Vec<(&str, bool)> = content
+        .split_whitespace()
+        .map(|word| (word, terms.contains(&normalise(word))))
+        .collect();
+
+    // Slide a window over the chunk, keeping the first window with the most matches
+    let width = SNIPPET_WORDS.min(words.len());
+    let mut matches = words[..width]
+        .iter()
+        .filter(|(_, matched)| *matched)
+        .count();
+    let (mut best_start, mut best_matches) = (0, matches);
+    for start in 1..=words.len() - width {
+        matches += usize::from(words[start + width - 1].1);
+        matches -= usize::from(words[start - 1].1);
+        if matches > best_matches {
+            best_matches = matches;
+            best_start = start;
+        }
+    }
+
+    // Show a few words of context before the first match
+    if let Some(first) = words[best_start..best_start + width]
+        .iter()
+        .position(|(_, matched)| *matched)
+    {
+        best_start = (best_start + first)
+            .saturating_sub(SNIPPET_CONTEXT)
+            .min(words.len() - width);
+    }
+
+    // Join the window into text, recording where the matched words are
+    let end = best_start + width;
+    let mut text = String::new();
+    let mut highlights = Vec::new();
+    if best_start > 0 {
+        text.push_str("… ");
+    }
+    for (i, (word, matched)) in words[best_start..end].iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        if *matched {
+            highlights.push((text.len(), text.len() + word.len()));
+        }
+        text.push_str(word);
+    }
+    if end < words.len() {
+        text.push_str(" …");
+    }
+    (text, highlights)
 }
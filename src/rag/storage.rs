@@ -0,0 +1,224 @@
+// This module defines the binary on-disk format of the local knowledge base
+// The index is stored as a single file:
+//   1. An 8-byte magic number identifying the file as a rinfomaid index
+//   2. A little-endian u32 format version
+//   3. A bincode-encoded header (analyzer/chunker settings and counts)
+//   4. The bincode-encoded documents, chunks and word index
+// Keeping the header separate lets us check compatibility without decoding the payload
+
+use crate::rag::chunking::{ChunkStrategy, ChunkUnit};
+use crate::rag::{Document, DocumentChunk, Posting};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// Magic number at the start of every index file
+const MAGIC: &[u8; 8] = b"RINFOIDX";
+
+// Version of the on-disk layout; bump whenever Document, DocumentChunk or the header change
+pub const FORMAT_VERSION: u32 = 7;
+
+// File name of the binary index inside the agentic directory
+pub const INDEX_FILE: &str = "index.bin";
+
+// Analyzer and chunker settings the index was built with
+// An index built with different settings would return inconsistent results, so it must be rebuilt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexSettings {
+    pub analyzer: String, // Name of the tokenizer used for the word index
+    pub chunk_strategy: ChunkStrategy, // How plain text is split into chunks
+    pub chunk_size: usize, // Maximum chunk size, in chunk_unit
+    pub chunk_overlap: usize, // Amount of text repeated between neighbouring chunks
+    pub chunk_unit: ChunkUnit, // Whether sizes are counted in words or approximate tokens
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            analyzer: "lowercase-alphanumeric".to_string(),
+            chunk_strategy: ChunkStrategy::Sentence,
+            chunk_size: 500,
+            chunk_overlap: 0,
+            chunk_unit: ChunkUnit::Words,
+        }
+    }
+}
+
+impl IndexSettings {
+    /// Check that the chunker settings make sense together
+    /// Returns: Result with a description of the problem if they do not
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            bail!("chunk size must be greater than zero");
+        }
+        if self.chunk_overlap >= self.chunk_size {
+            bail!(
+                "chunk overlap ({}) must be smaller than the chunk size ({})",
+                self.chunk_overlap,
+                self.chunk_size
+            );
+        }
+        Ok(())
+    }
+
+    /// Describe how the settings an index was built with differ from these settings
+    /// Parameters:
+    ///   - index: Settings stored in the index
+    ///
+    /// Returns: One description per differing setting, e.g. "chunk size 200 (index) vs 300 (current)"
+    pub fn differences(&self, index: &IndexSettings) -> Vec<String> {
+        let fields = [
+            ("analyzer", index.analyzer.clone(), self.analyzer.clone()),
+            (
+                "chunking strategy",
+                index.chunk_strategy.to_string(),
+                self.chunk_strategy.to_string(),
+            ),
+            (
+                "chunk size",
+                index.chunk_size.to_string(),
+                self.chunk_size.to_string(),
+            ),
+            (
+                "chunk overlap",
+                index.chunk_overlap.to_string(),
+                self.chunk_overlap.to_string(),
+            ),
+            (
+                "chunk unit",
+                index.chunk_unit.to_string(),
+                self.chunk_unit.to_string(),
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, built, current)| built != current)
+            .map(|(name, built, current)| {
+                format!("{} {} (index) vs {} (current)", name, built, current)
+            })
+            .collect()
+    }
+}
+
+// Header stored ahead of the index payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexHeader {
+    pub settings: IndexSettings, // Settings the index was built with
+    pub document_count: usize,   // Number of documents in the payload
+    pub chunk_count: usize,      // Number of chunks in the payload
+}
+
+// Borrowed view of the payload, used when writing
+// The word index is written in sorted order so identical input gives an identical file
+#[derive(Serialize)]
+struct IndexPayloadRef<'a> {
+    documents: &'a [Document],
+    chunks: &'a [DocumentChunk],
+    word_index: BTreeMap<&'a String, &'a Vec<Posting>>,
+}
+
+// Owned payload, used when reading
+#[derive(Deserialize)]
+pub struct IndexPayload {
+    pub documents: Vec<Document>,
+    pub chunks: Vec<DocumentChunk>,
+    pub word_index: HashMap<String, Vec<Posting>>,
+}
+
+/// Write the complete index to disk in the binary format
+/// Parameters:
+///   - path: Location of the index file
+///   - settings: Analyzer/chunker settings the index was built with
+///   - documents, chunks, word_index: The index contents
+///
+/// Returns: Result indicating success or failure
+pub fn write_index(
+    path: &Path,
+    settings: &IndexSettings,
+    documents: &[Document],
+    chunks: &[DocumentChunk],
+    word_index: &HashMap<String, Vec<Posting>>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+    let header = IndexHeader {
+        settings: settings.clone(),
+        document_count: documents.len(),
+        chunk_count: chunks.len(),
+    };
+    bincode::serialize_into(&mut writer, &header)?;
+
+    let payload = IndexPayloadRef {
+        documents,
+        chunks,
+        word_index: word_index.iter().collect(),
+    };
+    bincode::serialize_into(&mut writer, &payload)?;
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read and validate the magic number, format version and header of an index file
+/// Parameters:
+///   - reader: Reader positioned at the start of the index file
+///
+/// Returns: The decoded header, or an error if the file is not a compatible index
+fn read_header_from<R: Read>(reader: &mut R) -> Result<IndexHeader> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        bail!("file is not a rinfomaid index");
+    }
+
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        bail!(
+            "index format version {} is not supported (expected version {})",
+            version,
+            FORMAT_VERSION
+        );
+    }
+
+    Ok(bincode::deserialize_from(reader)?)
+}
+
+/// Read only the header of an index file (cheap, does not decode the payload)
+/// Parameters:
+///   - path: Location of the index file
+///
+/// Returns: The decoded header
+pub fn read_header(path: &Path) -> Result<IndexHeader> {
+    let mut reader = BufReader::new(File::open(path)?);
+    read_header_from(&mut reader)
+}
+
+/// Read a complete index file, checking it matches the expected settings
+/// Parameters:
+///   - path: Location of the index file
+///   - settings: Settings the caller expects the index to have been built with
+///
+/// Returns: The decoded header and payload, or an error if the index is stale or incompatible
+pub fn read_index(path: &Path, settings: &IndexSettings) -> Result<(IndexHeader, IndexPayload)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = read_header_from(&mut reader)?;
+
+    if &header.settings != settings {
+        bail!(
+            "index was built with different settings: {}",
+            settings.differences(&header.settings).join(", ")
+        );
+    }
+
+    let payload = bincode::deserialize_from(&mut reader)?;
+    Ok((header, payload))
+}
@@ -0,0 +1,225 @@
+// This module keeps the local knowledge base in sync with the data directory
+// It listens for filesystem notifications, debounces bursts of changes (editors
+// often write a file several times when saving) and incrementally re-indexes
+// only the documents that were affected
+
+use crate::colour_print;
+use crate::rag::manifest::{MANIFEST_FILE, Manifest};
+use crate::rag::{RagSystem, SyncAction};
+use anyhow::Result;
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+impl RagSystem {
+    /// Watch the data directories and re-index changed documents until interrupted
+    /// The index is brought up to date once at startup, then each debounced
+    /// batch of filesystem events triggers an incremental update
+    /// Parameters:
+    ///   - debounce: Quiet period to wait for before processing a burst of changes
+    ///
+    /// Returns: Result indicating failure to set up the watcher (runs until interrupted otherwise)
+    pub fn watch(&mut self, debounce: Duration) -> Result<()> {
+        // Catch up with anything that changed while we were not watching
+        self.build_local_model(false)?;
+
+        let manifest_path = self.agentic_dir.join(MANIFEST_FILE);
+        let mut manifest = Manifest::load(&manifest_path)?;
+
+        // Watch the canonical data directories so event paths can be mapped back
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(debounce, tx)?;
+        let mut roots = Vec::new();
+        for data_dir in &self.data_dirs {
+            let root = fs::canonicalize(data_dir)?;
+            debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+            colour_print(
+                &format!(
+                    "\t Watching {} for changes (press Ctrl-C to stop)...",
+                    data_dir.display()
+                ),
+                "cyan",
+            );
+            roots.push((root, data_dir.clone()));
+        }
+
+        for result in rx {
+            let events = match result {
+                Ok(events) => events,
+                Err(e) => {
+                    colour_print(&format!("\t Watch error: {}", e), "red");
+                    continue;
+                }
+            };
+
+            // Collapse the batch into a unique, ordered set of affected paths
+            let paths: BTreeSet<PathBuf> = events
+                .into_iter()
+                .filter_map(|event| to_data_path(&roots, &event.path))
+                .collect();
+
+            if let Err(e) = self.apply_changes(&paths, &mut manifest, &manifest_path) {
+                colour_print(&format!("\t Error updating local model: {}", e), "red");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-index a batch of changed paths and persist the result
+    /// Parameters:
+    ///   - paths: Affected paths, relative to the data directory as stored in Document::source
+    ///   - manifest: Manifest of indexed files, updated in place
+    ///   - manifest_path: Where to save the manifest afterwards
+    ///
+    /// Returns: Result indicating success or failure
+    fn apply_changes(
+        &mut self,
+        paths: &BTreeSet<PathBuf>,
+        manifest: &mut Manifest,
+        manifest_path: &Path,
+    ) -> Result<()> {
+        let mut updated = false;
+
+        // A directory that was created or moved in may be reported without its files,
+        // so sync every file under it (unchanged files are skipped by the manifest check)
+        let mut files: BTreeSet<PathBuf> = BTreeSet::new();
+        for path in paths {
+            if path.is_dir() {
+                files.extend(
+                    WalkDir::new(path)
+                        .into_iter()
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| entry.file_type().is_file())
+                        .map(|entry| entry.into_path()),
+                );
+            } else {
+                files.insert(path.clone());
+            }
+        }
+
+        for path in &files {
+            // Ignore files we cannot process (deleted paths are kept, since they may be
+            // a whole directory of documents)
+            if path.exists() && !Self::is_supported(path) {
+                continue;
+            }
+
+            // A file that cannot be indexed is reported without stopping the watcher
+            let documents_before = self.documents.len();
+            let action = match self.sync_file(path, manifest) {
+                Ok(action) => action,
+                Err(e) => {
+                    colour_print(
+                        &format!("\t Failed to index {}: {}", path.display(), e),
+                        "red",
+                    );
+                    // A stale copy of the document was dropped, so the saved index must follow
+                    updated |= self.documents.len() != documents_before;
+                    continue;
+                }
+            };
+            let label = match action {
+                SyncAction::Added => "Added",
+                SyncAction::Changed => "Updated",
+                SyncAction::Removed => "Removed",
+                SyncAction::Unchanged => continue,
+            };
+            colour_print(&format!("\t {}: {}", label, path.display()), "yellow");
+            updated = true;
+        }
+
+        if !updated {
+            return Ok(());
+        }
+
+        // Rebuild the word index from the updated chunks and save everything
+        self.word_index.clear();
+        self.sort_by_source();
+        self.build_word_index();
+        self.save_model()?;
+        manifest.save(manifest_path)?;
+
+        colour_print(
+            &format!(
+                "\t Local model updated: {} documents and {} chunks",
+                self.documents.len(),
+                self.chunks.len()
+            ),
+            "green",
+        );
+
+        Ok(())
+    }
+}
+
+/// Map an absolute event path back onto the data directory path used by the index
+/// Parameters:
+///   - roots: (canonical path, configured path) pairs of the watched data directories
+///   - path: Absolute path reported by the watcher
+///
+/// Returns: The equivalent path under a data directory, or None if it lies outside them
+fn to_data_path(roots: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+    roots.iter().find_map(|(root, data_dir)| {
+        path.strip_prefix(root)
+            .ok()
+            .map(|relative| data_dir.join(relative))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::storage::IndexSettings;
+
+    #[test]
+    fn new_directory_is_indexed_with_its_files() {
+        let root = std::env::temp_dir().join("rinfomaid-watch-tests");
+        let _ = fs::remove_dir_all(&root);
+        let data_dir = root.join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let mut rag_system = RagSystem::new(
+            &root.join("agentic"),
+            vec![data_dir.clone()],
+            IndexSettings::default(),
+        );
+        let manifest_path = root.join("agentic").join(MANIFEST_FILE);
+        let mut manifest = Manifest::default();
+
+        // A directory appears with documents in it, and only the directory is reported
+        let new_dir = data_dir.join("reports").join("2025");
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::write(
+            new_dir.join("summary.txt"),
+            "Quarterly summary of the results.",
+        )
+        .unwrap();
+        fs::write(new_dir.join("notes.md"), "# Notes\n\nFollow-up items.").unwrap();
+        fs::write(new_dir.join("image.bin"), [0u8, 1, 2]).unwrap();
+        let changed = BTreeSet::from([data_dir.join("reports")]);
+        rag_system
+            .apply_changes(&changed, &mut manifest, &manifest_path)
+            .unwrap();
+
+        let mut sources: Vec<&str> = rag_system
+            .documents
+            .iter()
+            .map(|doc| doc.source.as_str())
+            .collect();
+        sources.sort();
+        let expected = [new_dir.join("notes.md"), new_dir.join("summary.txt")];
+        let expected: Vec<String> = expected
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(sources, expected);
+        assert_eq!(manifest.files.len(), 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}